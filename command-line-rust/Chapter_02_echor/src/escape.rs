@@ -0,0 +1,72 @@
+// 反斜杠转义, echo -e、printf 的格式和 %b 共用
+//   \\ \a \b \c \e \f \n \r \t \v \xHH 以及八进制
+//   \c 之后的内容(包括 echo 的换行)都不再输出
+// 结果可能不是合法的 UTF-8 (如 \xff), 所以返回字节
+
+// 八进制转义的写法
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Octal {
+    Zero,  // echo -e 和 %b: \0NNN, 0 后最多三位
+    Plain, // printf 的格式: \NNN, 最多三位
+}
+
+// --------------------------------------------------
+// 返回转义后的字节, 以及是否遇到了 \c
+pub fn unescape(text: &str, octal: Octal) -> (Vec<u8>, bool) {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            // 普通字符, 以及末尾单独的反斜杠
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let c = bytes[i + 1];
+        i += 2;
+        match c {
+            b'\\' => out.push(b'\\'),
+            b'a' => out.push(0x07),
+            b'b' => out.push(0x08),
+            b'c' => return (out, true),
+            b'e' => out.push(0x1b),
+            b'f' => out.push(0x0c),
+            b'n' => out.push(b'\n'),
+            b'r' => out.push(b'\r'),
+            b't' => out.push(b'\t'),
+            b'v' => out.push(0x0b),
+            b'x' if bytes.get(i).is_some_and(u8::is_ascii_hexdigit) => {
+                let (value, len) = number(&bytes[i..], 16, 2);
+                out.push(value);
+                i += len;
+            }
+            b'0' if octal == Octal::Zero => {
+                let (value, len) = number(&bytes[i..], 8, 3);
+                out.push(value);
+                i += len;
+            }
+            b'0'..=b'7' if octal == Octal::Plain => {
+                // 第一位已经读过了, 退回去一起解析
+                let (value, len) = number(&bytes[i - 1..], 8, 3);
+                out.push(value);
+                i += len - 1;
+            }
+            // 无法识别的转义原样输出
+            _ => out.extend_from_slice(&[b'\\', c]),
+        }
+    }
+    (out, false)
+}
+
+// --------------------------------------------------
+// 读取最多 max 位 radix 进制的数字, 返回值和用掉的位数; 超过 255 时截断
+fn number(bytes: &[u8], radix: u32, max: usize) -> (u8, usize) {
+    let digits: Vec<u32> = bytes
+        .iter()
+        .take(max)
+        .map_while(|&b| (b as char).to_digit(radix))
+        .collect();
+    let value = digits.iter().fold(0, |acc, d| acc * radix + d);
+    (value as u8, digits.len())
+}
@@ -1,7 +1,18 @@
 use clap::{Command,Arg,ArgAction}; // App 重命名为 Command
 // cargo add clap
+use anyhow::{Result, anyhow};
+use escape::{Octal, unescape};
+use std::io::Write;
+
+mod escape; // 反斜杠转义
+mod printf; // --printf
 
 fn main() {
+    // 写入关闭的管道(如 | head)时安静地退出, 其他错误打印 "echor: 错误"
+    clr_core::exit(run());
+}
+
+fn run() -> Result<()> {
     // println!("{:?}",std::env::args()); //  cargo run -- -n xx yy 
     // // Args { inner: ["target/debug/echor", "-n", "xx", "yy"] }
 
@@ -13,7 +24,7 @@ fn main() {
             Arg::new("text") // with_name 替换为了 new
             .value_name("TEXT")
             .help("Input Text")
-            .required(true) // 必须有这个参参数
+            .required_unless_present("printf") // 除了 --printf 时, 必须有这个参参数
             .num_args(1..) //.min_values(1) // 出现次数至少一次
         )
         .arg(
@@ -22,7 +33,39 @@ fn main() {
             .help("Do not print newline")
             .action(ArgAction::SetTrue)//.takes_value(false) // 不接收具体值
         )
+        .arg(
+            Arg::new("escapes")
+            .short('e')
+            .help("Enable interpretation of backslash escapes")
+            .action(ArgAction::SetTrue)
+            .overrides_with("no_escapes") // -e 和 -E 以最后一个为准
+        )
+        .arg(
+            Arg::new("no_escapes")
+            .short('E')
+            .help("Disable interpretation of backslash escapes (default)")
+            .action(ArgAction::SetTrue)
+            .overrides_with("escapes")
+        )
+        .arg(
+            Arg::new("printf")
+            .long("printf")
+            .value_names(["FORMAT", "ARGS"])
+            .help("Format and print ARGS according to FORMAT \
+                   (%s %d %x %f %b conversions)")
+            .num_args(1..) // FORMAT 之后的所有参数原样交给 printf, 即使以 - 开头
+            .allow_hyphen_values(true)
+            .conflicts_with_all(["text", "omit_newline", "escapes", "no_escapes"])
+        )
         .get_matches(); // 解析参数
+
+    // echor --printf FORMAT ARGS...; 用选项而不是子命令, echor printf foo 仍然输出 "printf foo"
+    if let Some(values) = matches.get_many::<String>("printf") {
+        let values: Vec<String> = values.cloned().collect();
+        let (format, args) = values.split_first().expect("FORMAT is required");
+        let bytes = printf::printf(format, args).map_err(|e| anyhow!(e))?;
+        return write_out(&bytes);
+    }
     // println!("{:#?}",matches); // :#? 格式化打印
 
 
@@ -49,19 +92,40 @@ fn main() {
     // let omit_newline= matches.is_present("omit_newline");
     let omit_newline = matches.get_flag("omit_newline");
 
-    let ending = if omit_newline {" "} else { "\n"};
-    print!("{}{}",text.join(" "),ending); // Vec<String>  Vec.join()
+    let ending = if omit_newline {""} else { "\n"};
+    let text = text.join(" "); // Vec<String>  Vec.join()
+    if matches.get_flag("escapes") {
+        // 遇到 \c 时后面的内容和换行都不输出
+        let (mut bytes, stop) = unescape(&text, Octal::Zero);
+        if !stop {
+            bytes.extend(ending.as_bytes());
+        }
+        write_out(&bytes)
+    } else {
+        write_out(format!("{}{}",text,ending).as_bytes())
+    }
+
+}
 
+// 转义后可能不是合法的 UTF-8, 所以直接写字节
+fn write_out(bytes: &[u8]) -> Result<()> {
+    let mut out = clr_core::stdout();
+    out.write_all(bytes)?;
+    out.flush()?;
+    Ok(())
 }
 
 // Rust echo
 
-// Usage: echor [OPTIONS] <TEXT>...
+// Usage: echor [OPTIONS] [TEXT]...
 
 // Arguments:
-//   <TEXT>...  Input Text
+//   [TEXT]...  Input Text
 
 // Options:
-//   -n             Do not print newline
-//   -h, --help     Print help
-//   -V, --version  Print version
\ No newline at end of file
+//   -n                               Do not print newline
+//   -e                               Enable interpretation of backslash escapes
+//   -E                               Disable interpretation of backslash escapes (default)
+//       --printf <FORMAT> [ARGS]...  Format and print ARGS according to FORMAT (%s %d %x %f %b conversions)
+//   -h, --help                       Print help
+//   -V, --version                    Print version
\ No newline at end of file
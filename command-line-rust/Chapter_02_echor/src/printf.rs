@@ -0,0 +1,244 @@
+// echor --printf FORMAT ARGS...: 像 printf(1) 一样按格式输出, 不需要 shell
+//   转换说明: %s %d %x %f %b 和 %%
+//   标志: - 左对齐, 0 用 0 填充, + 和空格 正数前的符号; 然后是宽度和 .精度
+//     %-8s  %05d  %.3s  %8.2f  %.4x
+//   参数比转换说明多时重复使用格式; 参数不够时字符串当作空, 数字当作 0
+use crate::escape::{Octal, unescape};
+use std::{iter::Peekable, slice, str::Chars};
+
+#[derive(Debug, Default)]
+struct Spec {
+    left: bool,  // -
+    zero: bool,  // 0
+    plus: bool,  // +
+    space: bool, // 空格
+    width: usize,
+    precision: Option<usize>,
+}
+
+// --------------------------------------------------
+pub fn printf(format: &str, args: &[String]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    let mut args = args.iter();
+    loop {
+        let remaining = args.len();
+        // \c 或 %b 参数中的 \c 之后不再输出
+        if format_once(format, &mut args, &mut out)? {
+            break;
+        }
+        // 参数用完了, 或者格式中没有转换说明
+        if args.len() == 0 || args.len() == remaining {
+            break;
+        }
+    }
+    Ok(out)
+}
+
+// --------------------------------------------------
+// 按格式输出一遍, 遇到 \c 时返回 true
+fn format_once(
+    format: &str,
+    args: &mut slice::Iter<String>,
+    out: &mut Vec<u8>,
+) -> Result<bool, String> {
+    let mut chars = format.chars().peekable();
+    let mut literal = String::new(); // 还没处理转义的普通文本
+    while let Some(c) = chars.next() {
+        match c {
+            // 转义和后面的字符一起保留, 这样 \% 不会被当作转换说明
+            '\\' => {
+                literal.push(c);
+                if let Some(next) = chars.next() {
+                    literal.push(next);
+                }
+            }
+            '%' if chars.next_if_eq(&'%').is_some() => literal.push('%'),
+            '%' => {
+                if flush(&mut literal, out) {
+                    return Ok(true);
+                }
+                let spec = parse_spec(&mut chars);
+                let conversion = chars.next().ok_or_else(|| {
+                    format!(r#"missing conversion in "{format}""#)
+                })?;
+                let arg = args.next().map_or("", String::as_str);
+                let (bytes, stop) = convert(conversion, arg, &spec)?;
+                out.extend(bytes);
+                if stop {
+                    return Ok(true);
+                }
+            }
+            _ => literal.push(c),
+        }
+    }
+    Ok(flush(&mut literal, out))
+}
+
+// --------------------------------------------------
+// 输出处理过转义的普通文本, 遇到 \c 时返回 true
+fn flush(literal: &mut String, out: &mut Vec<u8>) -> bool {
+    let (bytes, stop) = unescape(literal, Octal::Plain);
+    out.extend(bytes);
+    literal.clear();
+    stop
+}
+
+// --------------------------------------------------
+// % 和转换字符之间的标志、宽度和精度
+fn parse_spec(chars: &mut Peekable<Chars>) -> Spec {
+    let mut spec = Spec::default();
+    while let Some(&c) = chars.peek() {
+        match c {
+            '-' => spec.left = true,
+            '0' => spec.zero = true,
+            '+' => spec.plus = true,
+            ' ' => spec.space = true,
+            _ => break,
+        }
+        chars.next();
+    }
+    spec.width = read_number(chars).unwrap_or(0);
+    if chars.next_if_eq(&'.').is_some() {
+        spec.precision = Some(read_number(chars).unwrap_or(0));
+    }
+    spec
+}
+
+// --------------------------------------------------
+fn read_number(chars: &mut Peekable<Chars>) -> Option<usize> {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits.parse().ok()
+}
+
+// --------------------------------------------------
+// 转换一个参数, 返回填充后的字节, 以及 %b 中是否遇到了 \c
+fn convert(
+    conversion: char,
+    arg: &str,
+    spec: &Spec,
+) -> Result<(Vec<u8>, bool), String> {
+    // 整数有精度时忽略 0 标志, 与 C 的 printf 一样
+    let zero_int = spec.zero && spec.precision.is_none();
+    match conversion {
+        's' => {
+            // 精度是最多输出的字符数
+            let text: String = match spec.precision {
+                Some(precision) => arg.chars().take(precision).collect(),
+                None => arg.to_string(),
+            };
+            Ok((pad(text.into_bytes(), spec, false), false))
+        }
+        'b' => {
+            let (mut bytes, stop) = unescape(arg, Octal::Zero);
+            if let Some(precision) = spec.precision {
+                bytes.truncate(precision);
+            }
+            Ok((pad(bytes, spec, false), stop))
+        }
+        'd' => {
+            let n = parse_int(arg)?;
+            let digits = min_digits(n.unsigned_abs().to_string(), spec);
+            let text = format!("{}{digits}", sign(n < 0, spec));
+            Ok((pad(text.into_bytes(), spec, zero_int), false))
+        }
+        'x' => {
+            // 负数按 64 位补码输出, 与 printf(1) 一样
+            let n = parse_int(arg)?;
+            let text = min_digits(format!("{:x}", n as u64), spec);
+            Ok((pad(text.into_bytes(), spec, zero_int), false))
+        }
+        'f' => {
+            let n = parse_float(arg)?;
+            let precision = spec.precision.unwrap_or(6);
+            let text =
+                format!("{}{:.precision$}", sign(n < 0.0, spec), n.abs());
+            Ok((pad(text.into_bytes(), spec, spec.zero), false))
+        }
+        _ => Err(format!("%{conversion}: invalid conversion specification")),
+    }
+}
+
+// --------------------------------------------------
+fn sign(negative: bool, spec: &Spec) -> &'static str {
+    match (negative, spec.plus, spec.space) {
+        (true, _, _) => "-",
+        (false, true, _) => "+",
+        (false, false, true) => " ",
+        _ => "",
+    }
+}
+
+// --------------------------------------------------
+// 整数的精度是最少的位数, 不够时前面补 0
+fn min_digits(digits: String, spec: &Spec) -> String {
+    match spec.precision {
+        Some(precision) => format!("{digits:0>precision$}"),
+        None => digits,
+    }
+}
+
+// --------------------------------------------------
+// 填充到宽度; 数字用 0 填充时 0 放在符号后面
+fn pad(mut body: Vec<u8>, spec: &Spec, zero: bool) -> Vec<u8> {
+    let fill = spec.width.saturating_sub(body.len());
+    if fill == 0 {
+        return body;
+    }
+    if spec.left {
+        body.extend(vec![b' '; fill]);
+        return body;
+    }
+    let (c, at) = match body.first() {
+        Some(b'-' | b'+' | b' ') if zero => (b'0', 1),
+        _ if zero => (b'0', 0),
+        _ => (b' ', 0),
+    };
+    body.splice(at..at, vec![c; fill]);
+    body
+}
+
+// --------------------------------------------------
+// 十进制, 0x 开头的十六进制, 0 开头的八进制, 或者 'c 表示字符的编码
+fn parse_int(arg: &str) -> Result<i64, String> {
+    let invalid = || format!(r#"invalid number "{arg}""#);
+    let text = arg.trim();
+    if text.is_empty() {
+        return Ok(0);
+    }
+    if let Some(rest) = text.strip_prefix(['\'', '"']) {
+        return rest.chars().next().map(|c| c as i64).ok_or_else(invalid);
+    }
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    // from_str_radix 也接受符号, 这里已经处理过了
+    if digits.starts_with(['+', '-']) {
+        return Err(invalid());
+    }
+    let value = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse()
+    }
+    .map_err(|_| invalid())?;
+    Ok(if negative { -value } else { value })
+}
+
+// --------------------------------------------------
+fn parse_float(arg: &str) -> Result<f64, String> {
+    match arg.trim() {
+        "" => Ok(0.0),
+        text => text
+            .parse()
+            .map_err(|_| format!(r#"invalid number "{arg}""#)),
+    }
+}
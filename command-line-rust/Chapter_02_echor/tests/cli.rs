@@ -108,4 +108,93 @@ fn run(args: &[&str],expected_file: &str) -> TestResult { // args  参切片 内
 // #[test]
 // fn hello2_no_newline() -> Result<()> {
 //     run(&["-n", "Hello", "there"], "tests/expected/hello2.n.txt")
-// }
\ No newline at end of file
+// }
+
+#[test]
+fn hello1_no_newline() -> TestResult {
+    run(&["Hello  there", "-n"], "tests/expected/hello1.n.txt") // -n 时结尾没有空格
+}
+
+#[test]
+fn hello2_no_newline() -> TestResult {
+    run(&["-n", "Hello", "there"], "tests/expected/hello2.n.txt")
+}
+
+// 输出可能不是合法的 UTF-8, 所以比较字节
+fn run_bytes(args: &[&str], expected: &[u8]) -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected.to_vec());
+    Ok(())
+}
+
+#[test]
+fn escapes() -> TestResult {
+    run_bytes(&["-e", r"a\tb\\c\n"], b"a\tb\\c\n\n")?;
+    run_bytes(&["-e", r"\0101\0\x42\xff\q"], b"A\0B\xff\\q\n")?;
+    run_bytes(&["-e", r"stop\c", "more"], b"stop")?; // \c 之后什么都不输出, 包括换行
+    run_bytes(&["-e", "-n", r"a\n"], b"a\n")
+}
+
+#[test]
+fn no_escapes() -> TestResult {
+    run_bytes(&[r"a\tb"], b"a\\tb\n")?; // 默认不处理转义
+    run_bytes(&["-e", "-E", r"a\tb"], b"a\\tb\n")?; // 以最后一个为准
+    run_bytes(&["-E", "-e", r"a\tb"], b"a\tb\n")
+}
+
+#[test]
+fn printf_strings() -> TestResult {
+    run_bytes(
+        &["--printf", r"[%s] [%-5s] [%5s] [%.2s]\n", "a", "b", "c", "defg"],
+        b"[a] [b    ] [    c] [de]\n",
+    )?;
+    run_bytes(&["--printf", r"%b|%s\n", r"a\tb", r"a\tb"], b"a\tb|a\\tb\n")?;
+    run_bytes(&["--printf", r"x%by\n", r"z\cw"], b"xz")?; // %b 中的 \c 结束所有输出
+    run_bytes(&["--printf", r"100%%\101\n"], b"100%A\n")
+}
+
+#[test]
+fn printf_numbers() -> TestResult {
+    run_bytes(
+        &["--printf", r"%05d %+d %.3d %-4d|\n", "42", "7", "5", "-3"],
+        b"00042 +7 005 -3  |\n",
+    )?;
+    run_bytes(&["--printf", r"%x %.4x %d %d\n", "255", "10", "0x1f", "'A"], b"ff 000a 31 65\n")?;
+    run_bytes(
+        &["--printf", r"%f %.2f %08.3f\n", "3.14159", "2.5", "-1.5"],
+        b"3.141590 2.50 -001.500\n",
+    )
+}
+
+#[test]
+fn printf_reuses_format() -> TestResult {
+    // 参数多时重复使用格式, 不够时当作空字符串和 0
+    run_bytes(&["--printf", r"%s=%d\n", "a", "1", "b", "2", "c"], b"a=1\nb=2\nc=0\n")
+}
+
+#[test]
+fn printf_is_text() -> TestResult {
+    // printf 不是子命令, 照常作为文本输出
+    run_bytes(&["printf", "foo"], b"printf foo\n")?;
+    run_bytes(&["--printf", "%s\n"], b"\n") // 没有参数时当作空字符串
+}
+
+#[test]
+fn printf_bad_number() -> TestResult {
+    Command::cargo_bin("echor")?
+        .args(["--printf", "%d", "abc"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(r#"invalid number "abc""#));
+    Ok(())
+}
+
+#[test]
+fn printf_hyphen_args() -> TestResult {
+    // FORMAT 之后以 - 开头的参数也原样输出, 不当作选项
+    run_bytes(&["--printf", r"%s|%s|%s\n", "-e", "-x", "--help"], b"-e|-x|--help\n")?;
+    run_bytes(&["--printf", r"%d\n", "-5"], b"-5\n")
+}
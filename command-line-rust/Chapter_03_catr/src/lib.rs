@@ -1,20 +1,11 @@
-use std::error::Error;
 use clap::Parser;
-use std::fs::File;
-use std::io::{self,BufRead,BufReader};
+use std::io::{BufRead, Write};
 use clap::{Command,Arg,ArgAction};
 
-fn open(filename: &str) -> MyResult<Box<dyn BufRead>> { // 返回值为错误信息，或者一个实现了BufRead特性的值
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))), // 如果输入的参数filename 是 "-",则从STDIN 读取
-        _ => Ok(Box::new(BufReader::new( // 其它情况
-            File::open(filename)? // ?解Result 返回值,获取value
-        ))),
-    }
-}
+// 打开文件使用 clr_core::open: "-" 从STDIN 读取, 错误信息中带有文件名
 
 
-type MyResult<T> = Result<T,Box<dyn Error>>;
+type MyResult<T> = anyhow::Result<T>; // 与 clr_core::exit 使用同样的错误类型
 
 // pub fn run() -> MyResult<()> { // pub 公共函数,提供外部访问
 //     println!("Hello, world");
@@ -24,35 +15,37 @@ type MyResult<T> = Result<T,Box<dyn Error>>;
 pub fn run(config: Config) -> MyResult<()> {
     // dbg!(config);
     //  cargo run -- tests/inputs/*.txt
+    let mut out = clr_core::stdout(); // 加锁带缓冲的STDOUT
     for filename in config.files {
         // println!("{}",filename);
-        match open(&filename) {
-            Err(err) => eprintln!("Failed to open {}: {}",filename,err), // 捕获错误到err 变量中,并将错误信息输出到STDERR 中
+        match clr_core::open(&filename) {
+            Err(err) => clr_core::warn(err), // 捕获错误到err 变量中,并将错误信息输出到STDERR 中: catr: 文件名: 错误
             Ok(file) => {
-                println!("Opend {}", filename);
+                writeln!(out, "Opend {}", filename)?;
                 let mut last_num = 0;
                 for (line_num, line) in file.lines().enumerate() { // 按行迭代
                     let line = line?;
                     if config.number_lines {
-                        println!("{:>6}\t{}",line_num+1, line);
+                        writeln!(out, "{:>6}\t{}",line_num+1, line)?;
                     // }else{
                     //     println!("{}", line);
                     // }
                     }else if config.number_nonblank_lines{
                         if !line.is_empty() { // 非空行
                             last_num += 1;
-                            println!("{:>6}\t{}",line_num, line);
+                            writeln!(out, "{:>6}\t{}",last_num, line)?; // 只给非空行编号
                         }else{
-                            println!(); // 空行就输出回车
+                            writeln!(out)?; // 空行就输出回车
                         }
                         
                     }else{
-                        println!("{}", line);
+                        writeln!(out, "{}", line)?;
                     }
                 }
             }
         }
     }
+    out.flush()?; // 写入错误(如管道关闭)在这里返回
     Ok(())
 }
 
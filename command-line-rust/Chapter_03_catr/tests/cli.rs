@@ -0,0 +1,33 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use pretty_assertions::assert_eq;
+use std::fs;
+
+const PRG: &str = "catr";
+const BUSTLE: &str = "tests/inputs/the-bustle.txt";
+
+// --------------------------------------------------
+// catr 在每个文件前输出 "Opend 文件名"
+fn run(args: &[&str], expected_file: &str) -> Result<()> {
+    let expected = fs::read_to_string(expected_file)?;
+    let output = Command::cargo_bin(PRG)?.args(args).output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let file = args.last().unwrap();
+    assert_eq!(stdout, format!("Opend {file}\n{expected}"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_n() -> Result<()> {
+    run(&["-n", BUSTLE], "tests/expected/the-bustle.txt.n.out")
+}
+
+// --------------------------------------------------
+#[test]
+fn bustle_b() -> Result<()> {
+    // 空行不占用行号, 后面的非空行接着编号
+    run(&["-b", BUSTLE], "tests/expected/the-bustle.txt.b.out")
+}
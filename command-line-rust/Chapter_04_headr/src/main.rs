@@ -1,7 +1,6 @@
 use anyhow::Result; // 错误处理库,简化错误处理
 use clap::Parser;   // 命令行参数解析库
-use std::fs::File;
-use std::io::{self, BufRead, BufReader, Read}; // 输入输出相关
+use std::io::{BufRead, Read, Write}; // 输入输出相关
 
 // cargo add  clap --features derive
 // cargo add  anyhow
@@ -37,27 +36,26 @@ struct Args {
 
 // --------------------------------------------------
 fn main() {
-    // 解析参数并运行,如果出错则打印错误并退出
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
-    }
+    // 解析参数并运行,如果出错则打印 "headr: 错误" 并以非0退出
+    clr_core::exit(run(Args::parse()));
 }
 
 // --------------------------------------------------
 fn run(args: Args) -> Result<()> {
     let num_files = args.files.len(); // 文件数量
+    let mut out = clr_core::stdout();
     // 遍历Vec向量
     for (file_num, filename) in args.files.iter().enumerate() {
-        match open(filename) {  // 尝试打开文件
-            Err(err) => eprintln!("{filename}: {err}"),  // 打开失败打印错误
+        match clr_core::open(filename) {  // 尝试打开文件
+            Err(err) => clr_core::warn(err),  // 打开失败打印错误, 继续处理下一个文件
             Ok(mut file) => {
                 // 如果多个文件,显示文件名标题
                 if num_files > 1 {
-                    println!(
+                    writeln!(
+                        out,
                         "{}==> {filename} <==",
                         if file_num > 0 { "\n" } else { "" }, // 非第一个文件(file_num -> index)前加空行
-                    );
+                    )?;
                 }
                 // 按字节模式或行模式处理
                 if let Some(num_bytes) = args.bytes { // 捕获参数 存入 num_bytes
@@ -90,10 +88,11 @@ fn run(args: Args) -> Result<()> {
                     &buffer[..bytes_read]  // 取前3个字节: [0, 0, 0]
                     */
                     let bytes_read = file.read(&mut buffer)?;  // 读取buffer提取的数据
-                    print!(
+                    write!(
+                        out,
                         "{}",
                         String::from_utf8_lossy(&buffer[..bytes_read])  // UTF-8 编码//替换无效序列为� // 将字节转换为字符串打印(支持非UTF-8字符)
-                    );
+                    )?;
                 } else {
                     let mut line = String::new();
                     for _ in 0..args.lines {
@@ -108,7 +107,7 @@ fn run(args: Args) -> Result<()> {
                         if bytes == 0 { // 读到文件末尾
                             break;
                         }
-                        print!("{line}");
+                        write!(out, "{line}")?;
                         line.clear();// 清空缓冲区供下次使用
                     }
                 }
@@ -116,13 +115,6 @@ fn run(args: Args) -> Result<()> {
         }
     }
 
+    out.flush()?;
     Ok(())
 }
-
-// --------------------------------------------------
-fn open(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))), // "-" 表示标准输入
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))), // 其他为普通文件
-    }
-}
@@ -5,8 +5,7 @@
 
 use anyhow::Result;
 use clap::Parser;
-use std::fs::File;
-use std::io::{self, BufRead, BufReader};
+use std::io::{BufRead, Write};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -43,10 +42,7 @@ struct FileInfo {
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {   // 解析参数并运行
-        eprintln!("{e}");
-        std::process::exit(1);             // 非零退出码表示错误
-    }
+    clr_core::exit(run(Args::parse()));   // 解析参数并运行, 非零退出码表示错误
 }
 
 // --------------------------------------------------
@@ -65,56 +61,53 @@ fn run(mut args: Args) -> Result<()> {
     let mut total_words = 0;
     let mut total_bytes = 0;
     let mut total_chars = 0;
+    let mut out = clr_core::stdout();
 
     // 遍历文件
     for filename in &args.files {
-        match open(filename) {
-            Err(err) => eprintln!("{filename}: {err}"),
-            Ok(file) => {
-                if let Ok(info) = count(file) {  // 返回 结构体FileInfo 获取信息 num_lines, num_words, num_bytes, num_chars
-                    println!(
+        match clr_core::open(filename) {
+            Err(err) => clr_core::warn(err),
+            Ok(file) => match count(file) {  // 返回 结构体FileInfo 获取信息 num_lines, num_words, num_bytes, num_chars
+                Err(err) => clr_core::warn(format!("{filename}: {err}")), // 读取失败(如目录)
+                Ok(info) => {
+                    writeln!(
+                        out,
                         "{}{}{}{}{}",
                         format_field(info.num_lines, args.lines), // 格式化 STDOUT 输出字符串
                         format_field(info.num_words, args.words),
                         format_field(info.num_bytes, args.bytes),
                         format_field(info.num_chars, args.chars),
-                        if filename == "-" {
+                        if clr_core::is_stdin(filename) {
                             "".to_string()
                         } else {
                             format!(" {filename}")
                         },
-                    );
+                    )?;
                     // 累加每行的计数
                     total_lines += info.num_lines;
                     total_words += info.num_words;
                     total_bytes += info.num_bytes;
                     total_chars += info.num_chars;
                 }
-            }
+            },
         }
     }
     // 如果处理了多个文件，显示总计
     if args.files.len() > 1 {
-        println!(
+        writeln!(
+            out,
             "{}{}{}{} total",
             format_field(total_lines, args.lines),
             format_field(total_words, args.words),
             format_field(total_bytes, args.bytes),
             format_field(total_chars, args.chars)
-        );
+        )?;
     }
 
+    out.flush()?;
     Ok(())
 }
 
-// --------------------------------------------------
-fn open(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),         // 从STDIN 读取
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))), // 从普通文件读取
-    }
-}
-
 // --------------------------------------------------
 fn format_field(value: usize, show: bool) -> String {
     if show {
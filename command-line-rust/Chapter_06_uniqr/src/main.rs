@@ -6,12 +6,9 @@
 // cargo add anyhow
 
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use clap::Parser;
-use std::{
-    fs::File,
-    io::{self, BufRead, BufReader, Write},
-};
+use std::io::{BufRead, Write};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about)]
@@ -32,27 +29,20 @@ struct Args {
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) { // 执行,并错误捕获
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    clr_core::exit(run(Args::parse())); // 执行,并错误捕获
 }
 
 // --------------------------------------------------
 // 主要的业务逻辑函数,返回 anyhow::Result<()>
 // anyhow::Result 是 std::result::Result<T, anyhow::Error> 的别名
 fn run(args: Args) -> Result<()> {
-    // 打开输入文件
-    // map_err 用于转换错误类型,将 open 返回的错误包装为 anyhow::Error
-    let mut file =
-        open(&args.in_file).map_err(|e| anyhow!("{}: {e}", args.in_file))?;
+    // 打开输入文件, 错误信息中已经带有文件名
+    let mut file = clr_core::open(&args.in_file)?;
     
     // 动态分发输出目标
     // Box<dyn Write> 是一个 trait object,可以在运行时决定具体类型
-    let mut out_file: Box<dyn Write> = match &args.out_file { // 对于未知大小的变量,动态存入堆,内存地址包装为Box智能指针,存储到栈
-        Some(out_name) => Box::new(File::create(out_name)?),
-        _ => Box::new(io::stdout()), // 其它情况(获取args.out_file失败),输出到STDOUT
-    };
+    // 没有 OUT_FILE 或者为 "-" 时输出到STDOUT
+    let mut out_file = clr_core::create(args.out_file.as_deref().unwrap_or("-"))?;
 
 
     /*
@@ -99,15 +89,7 @@ fn run(args: Args) -> Result<()> {
         line.clear(); // 清空 line 当前行缓存变量
     }
     print(count, &previous)?; // 补充最后一行的逻辑判断输出
+    out_file.flush()?;
 
     Ok(())
 }
-
-// --------------------------------------------------
-// 打开文件的辅助函数，返回一个实现了 BufRead trait 的对象
-fn open(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
-    }
-}
@@ -3,8 +3,7 @@ use clap::Parser;
 use csv::{ReaderBuilder, StringRecord, WriterBuilder};
 use regex::Regex;
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader},
+    io::{BufRead, Write},
     num::NonZeroUsize,
     ops::Range,
 };
@@ -64,10 +63,7 @@ enum Extract {
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
-    }
+    clr_core::exit(run(Args::parse()));
 }
 
 // --------------------------------------------------
@@ -98,9 +94,10 @@ fn run(args: Args) -> Result<()> {
     };
 
     // 处理文件
+    let mut out = clr_core::stdout();
     for filename in &args.files {
-        match open(filename) {
-            Err(err) => eprintln!("{filename}: {err}"),
+        match clr_core::open(filename) {
+            Err(err) => clr_core::warn(err),
             Ok(file) => match &extract {
                 // 域
                 Extract::Fields(field_pos) => {
@@ -113,38 +110,46 @@ fn run(args: Args) -> Result<()> {
                     // CSV 写入器配置
                     let mut wtr = WriterBuilder::new()
                         .delimiter(delimiter)       // 设置分割符
-                        .from_writer(io::stdout()); // 输出到标准输出
+                        .from_writer(&mut out); // 输出到标准输出
 
                     for record in reader.records() {
                         wtr.write_record(extract_fields( // extract_fields 提取指定列
                             &record?, field_pos,
-                        ))?;
+                        ))
+                        .map_err(csv_write_error)?;
                     }
+                    wtr.flush()?; // csv 写入器自己也有缓冲
                 }
                 // 字节
                 Extract::Bytes(byte_pos) => {  // Fields(PositionList) // type PositionList = Vec<Range<usize>>;
                     for line in file.lines() {
-                        println!("{}", extract_bytes(&line?, byte_pos));
+                        writeln!(out, "{}", extract_bytes(&line?, byte_pos))?;
                     }
                 }
                 // 字符
                 Extract::Chars(char_pos) => {
                     for line in file.lines() {
-                        println!("{}", extract_chars(&line?, char_pos));
+                        writeln!(out, "{}", extract_chars(&line?, char_pos))?;
                     }
                 }
             },
         }
     }
 
+    out.flush()?;
     Ok(())
 }
 
 // --------------------------------------------------
-fn open(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+// csv::Error 不提供 source(), 取出其中的 io::Error,
+// 这样写入关闭的管道时 clr_core::exit 能识别出来
+fn csv_write_error(err: csv::Error) -> anyhow::Error {
+    if !err.is_io_error() {
+        return err.into();
+    }
+    match err.into_kind() {
+        csv::ErrorKind::Io(e) => e.into(),
+        _ => unreachable!("is_io_error"),
     }
 }
 
@@ -156,15 +161,14 @@ fn open(filename: &str) -> Result<Box<dyn BufRead>> {
 // one less than the number represented by the original input.
 fn parse_index(input: &str) -> Result<usize> {
     let value_error = || anyhow!(r#"illegal list value: "{input}""#); // 闭包函数
-    input
-        .starts_with('+')
-        .then(|| Err(value_error())) // '+' 开头就报错
-        .unwrap_or_else(|| {         // 否则
-            input
-                .parse::<NonZeroUsize>() // clap解析 为非0数字
-                .map(|n| usize::from(n) - 1) // 数字减一
-                .map_err(|_| value_error()) // 捕获错误,调用 闭包函数
-        })
+    if input.starts_with('+') {
+        Err(value_error()) // '+' 开头就报错
+    } else {                // 否则
+        input
+            .parse::<NonZeroUsize>() // clap解析 为非0数字
+            .map(|n| usize::from(n) - 1) // 数字减一
+            .map_err(|_| value_error()) // 捕获错误,调用 闭包函数
+    }
 }
 
 // --------------------------------------------------
@@ -191,9 +195,7 @@ fn parse_pos(range: String) -> Result<PositionList> {  // 1,3-5,7
                 })
             })
         })
-        .collect::<Result<_, _>>() // 收集数字 VEC
-        .map_err(From::from) // 返回错误类型可能有多种 // 标准错误转换为自定义错误类型 
-        // From::from就会自动将其转换为anyhow::Error
+        .collect::<Result<_, _>>() // 收集数字 VEC, 错误已经是 anyhow::Error
 }
 
 // --------------------------------------------------
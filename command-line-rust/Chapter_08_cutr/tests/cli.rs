@@ -2,7 +2,7 @@ use anyhow::Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
-use rand::{distributions::Alphanumeric, Rng};
+use rand::{distr::Alphanumeric, Rng};
 use std::fs;
 
 const PRG: &str = "cutr";
@@ -12,7 +12,7 @@ const BOOKS: &str = "tests/inputs/books.tsv";
 
 // --------------------------------------------------
 fn random_string() -> String {
-    rand::thread_rng()
+    rand::rng()
         .sample_iter(&Alphanumeric)
         .take(7)
         .map(char::from)
@@ -37,7 +37,7 @@ fn skips_bad_file() -> Result<()> {
     Command::cargo_bin(PRG)?
         .args(["-f", "1", CSV, &bad, TSV])
         .assert()
-        .success()
+        .code(1) // 其他文件照常处理, 但以 1 退出
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
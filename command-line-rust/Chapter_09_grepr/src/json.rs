@@ -0,0 +1,133 @@
+// --json 输出: 每个事件一行 JSON, 格式参照 ripgrep 的 --json
+use crate::Line;
+use anyhow::Result;
+use serde::Serialize;
+use std::{ops::AddAssign, time::Duration};
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Begin {
+        path: Text<'a>,
+    },
+    Match(LineData<'a>),
+    Context(LineData<'a>),
+    End {
+        path: Text<'a>,
+        stats: Stats,
+    },
+    Summary {
+        elapsed_total: Elapsed,
+        stats: Stats,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Text<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LineData<'a> {
+    path: Text<'a>,
+    lines: Text<'a>,
+    line_number: usize,
+    absolute_offset: usize,
+    submatches: Vec<Submatch<'a>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Submatch<'a> {
+    #[serde(rename = "match")]
+    text: Text<'a>,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Elapsed {
+    secs: u64,
+    nanos: u32,
+    human: String,
+}
+
+// 匹配统计, 单个文件的统计累加得到总数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct Stats {
+    pub searches: usize,
+    pub searches_with_match: usize,
+    pub matched_lines: usize,
+    pub matches: usize,
+}
+
+impl Stats {
+    pub fn new(lines: &[Line]) -> Self {
+        let matched: Vec<_> = lines.iter().filter(|l| l.is_match).collect();
+        Stats {
+            searches: 1,
+            searches_with_match: usize::from(!matched.is_empty()),
+            matched_lines: matched.len(),
+            matches: matched.iter().map(|l| l.submatches.len()).sum(),
+        }
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Self) {
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+}
+
+impl Event<'_> {
+    pub fn summary(elapsed: Duration, stats: Stats) -> Self {
+        Event::Summary {
+            elapsed_total: Elapsed {
+                secs: elapsed.as_secs(),
+                nanos: elapsed.subsec_nanos(),
+                human: format!("{:.6}s", elapsed.as_secs_f64()),
+            },
+            stats,
+        }
+    }
+}
+
+// --------------------------------------------------
+// 一个文件的 begin/match/context/end 事件
+pub fn format_file(filename: &str, lines: &[Line], stats: &Stats) -> Result<String> {
+    let path = || Text { text: filename };
+    let mut events = vec![Event::Begin { path: path() }];
+
+    for line in lines {
+        let data = LineData {
+            path: path(),
+            lines: Text { text: &line.text },
+            line_number: line.number,
+            absolute_offset: line.offset,
+            submatches: line
+                .submatches
+                .iter()
+                .map(|&(start, end)| Submatch {
+                    text: Text { text: &line.text[start..end] },
+                    start,
+                    end,
+                })
+                .collect(),
+        };
+        events.push(if line.is_match {
+            Event::Match(data)
+        } else {
+            Event::Context(data)
+        });
+    }
+    events.push(Event::End { path: path(), stats: *stats });
+
+    let mut out = String::new();
+    for event in &events {
+        out += &serde_json::to_string(event)?;
+        out.push('\n');
+    }
+    Ok(out)
+}
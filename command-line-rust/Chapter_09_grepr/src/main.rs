@@ -1,17 +1,34 @@
 use anyhow::{anyhow, Result}; // 宏anyhow!()转化错误 //Result 类型
 use clap::Parser;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
 use regex::{Regex, RegexBuilder};
 use std::{
-    fs::{self, File},
-    io::{self, BufRead, BufReader},
+    collections::{BTreeMap, VecDeque},
+    ffi::OsStr,
+    fs,
+    io::{BufRead, Write},
     mem,
+    num::NonZeroUsize,
+    path::Path,
+    sync::{
+        mpsc::{self, SendError, SyncSender},
+        Mutex,
+    },
+    thread,
+    time::Instant,
 };
-use walkdir::WalkDir;
+
+mod json;
+use json::{Event, Stats};
 
 // cargo add  clap --features derive
 // cargo add anyhow
 // cargo add regex
-// cargo add walkdir
+// cargo add ignore globset
+// cargo add serde --features derive
+// cargo add serde_json
+// -z 的解压在 clr-core 的 decompress 特性中
 
 // cargo add --dev assert_cmd predicates pretty_assertions rand sys_info
 
@@ -36,20 +53,77 @@ struct Args {
     recursive: bool,    // 递归搜索目录
 
     /// Count occurrences
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "json")]
     count: bool,        // 统计匹配行数
 
     /// Invert match
     #[arg(short('v'), long("invert-match"))]
     invert: bool,       // 反转匹配(显示不匹配的行)
+
+    /// Search only files whose base name matches GLOB
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files whose base name matches GLOB
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Skip directories whose name matches GLOB when recursing
+    #[arg(long("exclude-dir"), value_name = "GLOB")]
+    exclude_dir: Vec<String>,
+
+    /// Honor .gitignore and .ignore files when recursing
+    #[arg(long)]
+    gitignore: bool,
+
+    /// Number of worker threads [default: number of CPUs]
+    #[arg(short('j'), long, value_name = "NUM",
+          value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+
+    /// Print NUM lines of trailing context
+    #[arg(short('A'), long("after-context"), value_name = "NUM")]
+    after: Option<usize>,
+
+    /// Print NUM lines of leading context
+    #[arg(short('B'), long("before-context"), value_name = "NUM")]
+    before: Option<usize>,
+
+    /// Print NUM lines of output context
+    #[arg(short('C'), long, value_name = "NUM")]
+    context: Option<usize>,
+
+    /// Print results as JSON Lines
+    #[arg(long)]
+    json: bool,
+
+    /// Search inside gzip/bzip2/xz/zstd compressed files
+    #[arg(short('z'), long)]
+    decompress: bool,
+}
+
+// 匹配行及其上下文行
+#[derive(Debug, PartialEq)]
+struct Line {
+    number: usize,                   // 行号, 从 1 开始
+    offset: usize,                   // 行首在文件中的字节偏移
+    text: String,                    // 包含行尾的 \r\n
+    is_match: bool,                  // false 表示上下文行
+    submatches: Vec<(usize, usize)>, // 匹配在行内的字节区间, 只在 --json 时记录
+}
+
+// 递归搜索时的文件过滤规则
+#[derive(Debug)]
+struct FileFilter {
+    include: Option<GlobSet>, // None 表示不限制
+    exclude: GlobSet,
+    exclude_dir: GlobSet,
+    gitignore: bool,
 }
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{}", e);
-        std::process::exit(1);
-    }
+    clr_core::exit(run(Args::parse()));
 }
 
 // --------------------------------------------------
@@ -60,46 +134,152 @@ fn run(args: Args) -> Result<()> {
         .build() // build 检验正则表达式是否正规
         .map_err(|_| anyhow!(r#"Invalid pattern "{}""#, args.pattern))?;
 
-    let entries = find_files(&args.files, args.recursive);
-    let num_files = entries.len();  // 文件数量
-    let print = |fname: &str, val: &str| {  // 闭包函数 文件前缀fname,文件名val
-        if num_files > 1 {
-            print!("{fname}:{val}");
+    let filter = FileFilter {
+        include: if args.include.is_empty() {
+            None
+        } else {
+            Some(build_globset(&args.include)?)
+        },
+        exclude: build_globset(&args.exclude)?,
+        exclude_dir: build_globset(&args.exclude_dir)?,
+        gitignore: args.gitignore,
+    };
+
+    let num_threads = args.threads.map_or_else(
+        || thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        |n| n as usize,
+    );
+
+    // 在后台线程中边遍历边发送路径, 工作线程不必等整棵目录树遍历完
+    let (path_tx, path_rx) = mpsc::sync_channel(num_threads);
+    let (paths, recursive) = (args.files.clone(), args.recursive);
+    thread::spawn(move || find_files(&paths, recursive, &filter, &path_tx));
+
+    // 找到第二个文件后就能确定是否需要文件名前缀
+    let head: Vec<_> = path_rx.iter().take(2).collect();
+    let multiple = head.len() > 1;
+    let num_workers = if multiple { num_threads } else { head.len() };
+
+    let context = (
+        args.before.or(args.context).unwrap_or(0),
+        args.after.or(args.context).unwrap_or(0),
+    );
+    let show_context = context != (0, 0);
+
+    // 每个文件的输出先在工作线程中拼成一块, 再按输入顺序整体打印
+    let search = |entry: &Result<String>| -> Result<(String, Stats)> {
+        let filename = match entry {
+            Err(e) => return Err(anyhow!("{e}")), // 验证文件有效
+            Ok(filename) => filename,
+        };
+        // 只有 -z 时才尝试解压, 输出中的文件名保持不变
+        let file = if args.decompress {
+            clr_core::open_decompressed(filename)?
         } else {
-            print!("{val}");
+            clr_core::open(filename)?
+        };
+        let lines = find_lines(file, &pattern, args.invert, args.json, context)
+            .map_err(|e| anyhow!("{filename}: {e}"))?;
+        let stats = Stats::new(&lines);
+        if args.json {
+            return Ok((json::format_file(filename, &lines, &stats)?, stats));
         }
+
+        let prefix = |sep: char| {
+            if multiple {
+                format!("{filename}{sep}") // 多个文件时加上文件名前缀
+            } else {
+                String::new()
+            }
+        };
+
+        let mut out = String::new();
+        if args.count {
+            out += &format!("{}{}\n", prefix(':'), stats.matched_lines);
+        } else {
+            let mut last = None; // 上一次输出的行号
+            for line in &lines {
+                // 有上下文时, 不相邻的两组之间用 -- 分隔
+                if show_context && last.is_some_and(|n| n + 1 < line.number) {
+                    out += "--\n";
+                }
+                out += &prefix(if line.is_match { ':' } else { '-' });
+                out += &line.text;
+                last = Some(line.number);
+            }
+        }
+        Ok((out, stats))
     };
 
-    for entry in entries {
-        match entry {
-            Err(e) => eprintln!("{e}"), // 验证文件有效
-            Ok(filename) => match open(&filename) { // 有效不报错就打开文件
-                Err(e) => eprintln!("{filename}: {e}"),
-                Ok(file) => match find_lines(file, &pattern, args.invert) {  // 成功就按行查找
-                    Err(e) => eprintln!("{e}"),
-                    Ok(matches) => { // 成功找到具体行, 返回值赋值给 matches
-                        if args.count {
-                            print(&filename, &format!("{}\n", matches.len()));
-                        } else {
-                            for line in &matches {
-                                print(&filename, line);
-                            }
-                        }
+    let start = Instant::now();
+    let mut total = Stats::default();
+    // 每输出一个结果才归还一个许可, 暂存待输出的结果最多 window 个
+    let window = 2 * num_threads;
+    let (permit_tx, permit_rx) = mpsc::sync_channel(window);
+    for _ in 0..window {
+        permit_tx.send(())?;
+    }
+    let paths = head.into_iter().chain(path_rx).enumerate(); // 带上输入顺序的下标
+    let jobs = Mutex::new((permit_rx, paths));
+    let (tx, rx) = mpsc::channel();
+    let mut out = clr_core::stdout();
+    thread::scope(|s| -> Result<()> {
+        let permits = permit_tx; // 移入闭包, 提前返回时工作线程随之停下
+        for _ in 0..num_workers {
+            let (tx, jobs, search) = (tx.clone(), &jobs, &search);
+            s.spawn(move || loop {
+                let job = {
+                    let mut jobs = jobs.lock().unwrap();
+                    let (permits, paths) = &mut *jobs;
+                    permits.recv().ok().and_then(|()| paths.next())
+                };
+                match job {
+                    Some((i, entry)) if tx.send((i, search(&entry))).is_ok() => {}
+                    _ => break,
+                }
+            });
+        }
+        drop(tx); // 所有工作线程结束后 rx 迭代才会结束
+
+        // 结果可能乱序到达, 先暂存, 等到下一个下标就绪时再输出
+        let mut pending = BTreeMap::new();
+        let mut wanted = 0;
+        for (i, result) in rx {
+            pending.insert(i, result);
+            while let Some(result) = pending.remove(&wanted) {
+                match result {
+                    Err(e) => clr_core::warn(e),
+                    Ok((text, stats)) => {
+                        // 写入失败(如管道关闭)时返回, rx 被丢弃后工作线程也会停下
+                        write!(out, "{text}")?;
+                        total += stats;
                     }
-                },
-            },
+                }
+                wanted += 1;
+                // 工作线程都已退出时许可无人接收, 忽略即可
+                let _ = permits.send(());
+            }
         }
+        Ok(())
+    })?;
+
+    if args.json {
+        writeln!(out, "{}", serde_json::to_string(&Event::summary(start.elapsed(), total))?)?;
     }
 
+    out.flush()?;
     Ok(())
 }
 
 // --------------------------------------------------
-fn open(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(File::open(filename)?))),
+fn build_globset(globs: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(
+            Glob::new(glob).map_err(|_| anyhow!(r#"Invalid glob "{glob}""#))?,
+        );
     }
+    Ok(builder.build()?)
 }
 
 // --------------------------------------------------
@@ -107,64 +287,126 @@ fn find_lines<T: BufRead>(
     mut file: T,
     pattern: &Regex,
     invert: bool,
-) -> Result<Vec<String>> {
-    let mut matches = vec![];
+    spans: bool, // 记录匹配区间, 只有 --json 用到
+    (before, after): (usize, usize), // 前后上下文行数
+) -> Result<Vec<Line>> {
+    let mut lines = vec![];
+    let mut pending = VecDeque::with_capacity(before); // 可能成为前置上下文的行
+    let mut after_left = 0; // 还需输出的后置上下文行数
     let mut line = String::new();
+    let (mut number, mut offset) = (0, 0);
 
     loop {
         let bytes = file.read_line(&mut line)?; // 包含\r\n
         if bytes == 0 { // 读取到 EOF
             break;
         }
-        // 使用异或操作实现反转匹配逻辑：
-        // - 如果invert为false:pattern匹配成功时条件为true
-        // - 如果invert为true:pattern匹配失败时条件为true
-        if pattern.is_match(&line) ^ invert {
-            matches.push(mem::take(&mut line));
+        number += 1;
+
+        // 反转匹配时只要求整行不匹配, 没有匹配区间可记录
+        let submatches: Vec<_> = if spans && !invert {
+            pattern.find_iter(&line).map(|m| (m.start(), m.end())).collect()
+        } else {
+            vec![]
+        };
+        let is_match = if spans && !invert {
+            !submatches.is_empty()
+        } else {
+            pattern.is_match(&line) != invert
+        };
+        let found = Line {
+            number,
+            offset,
+            text: mem::take(&mut line),
             // mem::take取出line的内容(移动)，同时在原位置放入空字符串
             // 这样可以避免复制字符串，提高性能
+            is_match,
+            submatches,
+        };
+        offset += bytes;
+
+        if is_match {
+            lines.extend(pending.drain(..));
+            lines.push(found);
+            after_left = after;
+        } else if after_left > 0 {
+            lines.push(found);
+            after_left -= 1;
+        } else if before > 0 {
+            if pending.len() == before {
+                pending.pop_front();
+            }
+            pending.push_back(found);
         }
-        line.clear();
     }
 
-    Ok(matches)
+    Ok(lines)
 }
 
 // --------------------------------------------------
-fn find_files(paths: &[String], recursive: bool) -> Vec<Result<String>> {
-    let mut results = vec![];
+fn find_files(
+    paths: &[String],
+    recursive: bool,
+    filter: &FileFilter,
+    results: &SyncSender<Result<String>>, // 接收端关闭后停止遍历
+) -> Result<(), SendError<Result<String>>> {
+    // 按文件名(不含目录)做 --include/--exclude 过滤
+    let wanted = |name: &OsStr| {
+        filter.include.as_ref().is_none_or(|set| set.is_match(name))
+            && !filter.exclude.is_match(name)
+    };
 
     for path in paths {
         match path.as_str() {
-            "-" => results.push(Ok(path.to_string())),
+            stdin if clr_core::is_stdin(stdin) => results.send(Ok(path.to_string()))?,
             _ => match fs::metadata(path) { // 获取文件元数据
                 Ok(metadata) => { // 成功就将 fs::metadata(path) 赋值给 metadata
                     if metadata.is_dir() { // 如果是目录
                         if recursive { // 是否遍历
-                            for entry in WalkDir::new(path)
-                                .into_iter()
+                            let exclude_dir = filter.exclude_dir.clone();
+                            let gitignore = filter.gitignore;
+                            let walker = WalkBuilder::new(path)
+                                .standard_filters(false) // 默认不忽略任何文件
+                                .git_ignore(gitignore)
+                                .git_exclude(gitignore)
+                                .ignore(gitignore)
+                                .require_git(false) // 不在 git 仓库中也读取 .gitignore
+                                .sort_by_file_name(|a, b| a.cmp(b))
+                                .filter_entry(move |e| {
+                                    // 根目录本身不参与 --exclude-dir 匹配
+                                    e.depth() == 0
+                                        || !e.file_type().is_some_and(|t| t.is_dir())
+                                        || !(exclude_dir.is_match(e.file_name())
+                                            || gitignore && e.file_name() == ".git")
+                                })
+                                .build();
+                            for entry in walker
                                 .flatten()  // 过滤掉错误条目
-                                .filter(|e| e.file_type().is_file()) // 只保留文件
+                                .filter(|e| e.file_type().is_some_and(|t| t.is_file())) // 只保留文件
+                                .filter(|e| wanted(e.file_name()))
                             {
-                                results.push(Ok(entry
+                                results.send(Ok(entry
                                     .path()
                                     .display()
-                                    .to_string()));
+                                    .to_string()))?;
                             }
                         } else {
                             results // 传入错误
-                                .push(Err(anyhow!("{path} is a directory")));
+                                .send(Err(anyhow!("{path} is a directory")))?;
                         }
                     } else if metadata.is_file() { // 如果是文件
-                        results.push(Ok(path.to_string())); // 存入Ok  Result(Ok(),Err())
+                        let name = Path::new(path).file_name().unwrap_or_default();
+                        if wanted(name) {
+                            results.send(Ok(path.to_string()))?; // 存入Ok  Result(Ok(),Err())
+                        }
                     }// // 这里没有处理其他类型的文件(如符号链接)
                 }
-                Err(e) => results.push(Err(anyhow!("{path}: {e}"))), //  fs::metadata(path) 失败 存入报错
+                Err(e) => results.send(Err(anyhow!("{path}: {e}")))?, //  fs::metadata(path) 失败 存入报错
             },
         }
     }
 
-    results
+    Ok(())
 }
 
 // --------------------------------------------------
@@ -2,7 +2,7 @@ use anyhow::Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
-use rand::{distributions::Alphanumeric, Rng};
+use rand::{distr::Alphanumeric, Rng};
 use std::{fs, path::Path};
 use sys_info::os_type;
 
@@ -16,7 +16,7 @@ const INPUTS_DIR: &str = "tests/inputs";
 // --------------------------------------------------
 fn gen_bad_file() -> String {
     loop {
-        let filename: String = rand::thread_rng()
+        let filename: String = rand::rng()
             .sample_iter(&Alphanumeric)
             .take(7)
             .map(char::from)
@@ -57,6 +57,7 @@ fn warns_bad_file() -> Result<()> {
     Command::cargo_bin(PRG)?
         .args(["foo", &bad])
         .assert()
+        .code(1)
         .stderr(predicate::str::is_match(expected)?);
     Ok(())
 }
@@ -285,3 +286,257 @@ fn stdin_insensitive_count() -> Result<()> {
     assert_eq!(stdout, expected);
     Ok(())
 }
+
+// --------------------------------------------------
+// 在临时目录下生成一棵小的源码树, 用于测试递归搜索的过滤规则
+fn gen_tree(name: &str) -> Result<String> {
+    let root = std::env::temp_dir().join(format!("grepr-{name}-{}", std::process::id()));
+    if root.exists() {
+        fs::remove_dir_all(&root)?;
+    }
+    for dir in ["src", "target", "vendor"] {
+        fs::create_dir_all(root.join(dir))?;
+    }
+    fs::write(root.join(".gitignore"), "vendor/\n*.log\n")?;
+    fs::write(root.join("README.md"), "fox in readme\n")?;
+    fs::write(root.join("build.log"), "fox in log\n")?;
+    fs::write(root.join("src/main.rs"), "// fox in main\n")?;
+    fs::write(root.join("src/lib.rs"), "// no match here\n")?;
+    fs::write(root.join("target/out.rs"), "// fox in target\n")?;
+    fs::write(root.join("vendor/dep.rs"), "// fox in vendor\n")?;
+    Ok(root.display().to_string())
+}
+
+// --------------------------------------------------
+fn run_tree(args: &[&str], root: &str, expected: &[&str]) -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(args)
+        .arg(root)
+        .output()
+        .expect("fail");
+    // 先删除临时目录, 断言失败时也不会留下
+    fs::remove_dir_all(root)?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    let expected: String = expected
+        .iter()
+        .map(|line| format!("{root}/{line}\n"))
+        .collect();
+    assert_eq!(stdout, expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_include() -> Result<()> {
+    let root = gen_tree("include")?;
+    run_tree(
+        &["-r", "--include=*.rs", "fox"],
+        &root,
+        &[
+            "src/main.rs:// fox in main",
+            "target/out.rs:// fox in target",
+            "vendor/dep.rs:// fox in vendor",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_exclude() -> Result<()> {
+    let root = gen_tree("exclude")?;
+    run_tree(
+        &["-r", "--exclude=*.rs", "--exclude", "*.md", "fox"],
+        &root,
+        &["build.log:fox in log"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_exclude_dir() -> Result<()> {
+    let root = gen_tree("exclude-dir")?;
+    run_tree(
+        &["-r", "--exclude-dir=target", "--exclude-dir=vendor", "fox"],
+        &root,
+        &[
+            "README.md:fox in readme",
+            "build.log:fox in log",
+            "src/main.rs:// fox in main",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_exclude_dir_root() -> Result<()> {
+    // 作为参数给出的目录本身不受 --exclude-dir 影响
+    let root = gen_tree("exclude-root")?;
+    run_tree(
+        &["-r", "--exclude-dir=grepr-exclude-root-*", "fox"],
+        &root,
+        &[
+            "README.md:fox in readme",
+            "build.log:fox in log",
+            "src/main.rs:// fox in main",
+            "target/out.rs:// fox in target",
+            "vendor/dep.rs:// fox in vendor",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_gitignore() -> Result<()> {
+    let root = gen_tree("gitignore")?;
+    run_tree(
+        &["-r", "--gitignore", "-j", "3", "fox"],
+        &root,
+        &[
+            "README.md:fox in readme",
+            "src/main.rs:// fox in main",
+            "target/out.rs:// fox in target",
+        ],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_count_single_thread() -> Result<()> {
+    let root = gen_tree("count")?;
+    run_tree(
+        &["-rc", "-j", "1", "--include=*.rs", "fox"],
+        &root,
+        &["src/lib.rs:0", "src/main.rs:1", "target/out.rs:1", "vendor/dep.rs:1"],
+    )
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive_many_files_in_order() -> Result<()> {
+    // 文件数远多于线程数时, 输出仍按遍历顺序排列
+    let root = gen_tree("many")?;
+    let names: Vec<_> = (0..200).map(|i| format!("f{i:03}.txt")).collect();
+    for name in &names {
+        fs::write(Path::new(&root).join("src").join(name), "fox\n")?;
+    }
+    let expected: Vec<_> =
+        names.iter().map(|name| format!("src/{name}:fox")).collect();
+    let expected: Vec<_> = expected.iter().map(String::as_str).collect();
+    run_tree(&["-r", "-j", "4", "--include=*.txt", "fox"], &root, &expected)
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_glob() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--include", "[z-a", "fox", "-"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"Invalid glob "[z-a""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+const LINES: &str = "one fox\ntwo\nthree\nfour fox fox\nfive\nsix\nseven\neight fox\n";
+
+// --------------------------------------------------
+#[test]
+fn context_lines() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-A", "1", "-B", "1", "eight|four"])
+        .write_stdin(LINES)
+        .assert()
+        .success()
+        .stdout("three\nfour fox fox\nfive\n--\nseven\neight fox\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json_events() -> Result<()> {
+    let output = Command::cargo_bin(PRG)?
+        .args(["--json", "-C", "1", "fox|three", "-"])
+        .write_stdin(LINES)
+        .output()
+        .expect("fail");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
+    let events: Vec<&str> = stdout.lines().collect();
+    assert_eq!(events.len(), 10);
+    assert_eq!(events[0], r#"{"type":"begin","data":{"path":{"text":"-"}}}"#);
+    assert_eq!(
+        events[4],
+        r#"{"type":"match","data":{"path":{"text":"-"},"lines":{"text":"four fox fox\n"},"line_number":4,"absolute_offset":18,"submatches":[{"match":{"text":"fox"},"start":5,"end":8},{"match":{"text":"fox"},"start":9,"end":12}]}}"#
+    );
+    assert_eq!(
+        events[5],
+        r#"{"type":"context","data":{"path":{"text":"-"},"lines":{"text":"five\n"},"line_number":5,"absolute_offset":31,"submatches":[]}}"#
+    );
+    assert_eq!(
+        events[8],
+        r#"{"type":"end","data":{"path":{"text":"-"},"stats":{"searches":1,"searches_with_match":1,"matched_lines":4,"matches":5}}}"#
+    );
+    assert!(events[9].starts_with(r#"{"type":"summary","data":{"elapsed_total":{"secs":0,"#));
+    assert!(events[9].ends_with(r#""stats":{"searches":1,"searches_with_match":1,"matched_lines":4,"matches":5}}}"#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_json_count() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["--json", "-c", "fox"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn decompress() -> Result<()> {
+    use std::io::Write;
+
+    let dir = std::env::temp_dir().join(format!("grepr-z-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let gz = dir.join("lines.txt.gz");
+    let mut encoder =
+        flate2::write::GzEncoder::new(fs::File::create(&gz)?, Default::default());
+    encoder.write_all(LINES.as_bytes())?;
+    encoder.finish()?;
+    // 扩展名不对也按魔数识别
+    let zst = dir.join("lines.log");
+    fs::write(&zst, zstd::encode_all(LINES.as_bytes(), 0)?)?;
+
+    let (gz, zst) = (gz.display().to_string(), zst.display().to_string());
+    Command::cargo_bin(PRG)?
+        .args(["-z", "eight", &gz, &zst])
+        .assert()
+        .success()
+        .stdout(format!("{gz}:eight fox\n{zst}:eight fox\n"));
+
+    // 标准输入同样支持
+    Command::cargo_bin(PRG)?
+        .args(["-zc", "fox"])
+        .write_stdin(fs::read(&gz)?)
+        .assert()
+        .success()
+        .stdout("3\n");
+
+    // 扩展名是 .gz 但不是压缩文件时原样搜索, 空文件没有匹配
+    let plain = dir.join("plain.gz");
+    fs::write(&plain, LINES)?;
+    let empty = dir.join("empty.gz");
+    fs::write(&empty, "")?;
+    let (plain, empty) = (plain.display().to_string(), empty.display().to_string());
+    Command::cargo_bin(PRG)?
+        .args(["-z", "eight", &plain, &empty])
+        .assert()
+        .success()
+        .stdout(format!("{plain}:eight fox\n"));
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
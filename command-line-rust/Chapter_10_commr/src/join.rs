@@ -0,0 +1,239 @@
+// commr join: 按指定字段连接两个已排序的文件, 复用 comm 的归并逻辑
+use crate::{compare, merge_walk, open_both, Column::*, OrderArgs};
+use anyhow::{bail, Result};
+use clap::Parser;
+use std::{io::Write, iter};
+
+#[derive(Debug, Parser)]
+#[command(name = "commr join", bin_name = "commr join", version)]
+/// Join lines of two sorted files on a common field
+pub struct Args {
+    /// Input file 1
+    #[arg()]
+    file1: String,
+
+    /// Input file 2
+    #[arg()]
+    file2: String,
+
+    /// Join on this FIELD of file 1
+    #[arg(short('1'), value_name = "FIELD", default_value_t = 1,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    field1: u64,
+
+    /// Join on this FIELD of file 2
+    #[arg(short('2'), value_name = "FIELD", default_value_t = 1,
+          value_parser = clap::value_parser!(u64).range(1..))]
+    field2: u64,
+
+    /// Use CHAR as input and output field separator
+    #[arg(short('t'), value_name = "CHAR")]
+    delimiter: Option<char>, // 默认以连续空白分隔字段, 输出用一个空格
+
+    /// Also print unpairable lines from file FILENUM
+    #[arg(short('a'), value_name = "FILENUM",
+          value_parser = clap::value_parser!(u8).range(1..=2))]
+    unpaired: Vec<u8>,
+
+    /// Print only unpairable lines from file FILENUM
+    #[arg(short('v'), value_name = "FILENUM",
+          value_parser = clap::value_parser!(u8).range(1..=2))]
+    only_unpaired: Vec<u8>,
+
+    /// Output fields as given by FORMAT, e.g. "0,1.2,2.3"
+    #[arg(short('o'), value_name = "FORMAT")]
+    format: Option<String>,
+
+    /// Replace missing fields in -o output with EMPTY
+    #[arg(short('e'), value_name = "EMPTY", default_value = "")]
+    empty: String,
+
+    /// Treat the first line of each file as a header
+    #[arg(long)]
+    header: bool, // 表头不参与排序检查和配对, 直接连接输出
+
+    #[command(flatten)]
+    order: OrderArgs,
+}
+
+// -o 中的一个输出字段
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutField {
+    Join,                 // 0: 连接字段
+    Field(usize, usize),  // N.M: 文件 N 的第 M 个字段(从 0 开始存储)
+}
+
+// 连接字段相同的相邻行
+struct Group {
+    key: String,
+    lines: Vec<Vec<String>>, // 每行拆分后的字段
+}
+
+// --------------------------------------------------
+pub fn run(args: Args) -> Result<()> {
+    let order = &args.order;
+    let format = args.format.as_deref().map(parse_format).transpose()?;
+    let (field1, field2) = (args.field1 as usize - 1, args.field2 as usize - 1);
+    let separator = args.delimiter.map_or(" ".to_string(), String::from);
+    let terminator = if order.zero_terminated { '\0' } else { '\n' };
+
+    let (mut lines1, mut lines2) = open_both(&args.file1, &args.file2, order)?;
+    let headers = if args.header {
+        let split = |line: String| split_fields(&line, args.delimiter);
+        (lines1.next().map(split), lines2.next().map(split))
+    } else {
+        (None, None)
+    };
+    let groups1 = groups(lines1, field1, args.delimiter, order);
+    let groups2 = groups(lines2, field2, args.delimiter, order);
+    let mut out = clr_core::stdout();
+
+    // 输出一行, 未配对的行只有一侧有字段
+    let mut print = |fields1: Option<&[String]>,
+                     fields2: Option<&[String]>|
+     -> Result<()> {
+        let key = fields1
+            .and_then(|f| f.get(field1))
+            .or_else(|| fields2.and_then(|f| f.get(field2)))
+            .map_or("", String::as_str);
+
+        let fields: Vec<&str> = match &format {
+            Some(format) => format
+                .iter()
+                .map(|field| match *field {
+                    OutField::Join => key,
+                    OutField::Field(num, idx) => {
+                        let fields = if num == 1 { fields1 } else { fields2 };
+                        fields
+                            .and_then(|f| f.get(idx))
+                            .map_or(args.empty.as_str(), String::as_str)
+                    }
+                })
+                .collect(),
+            // 默认格式: 连接字段, 文件1的其余字段, 文件2的其余字段
+            None => iter::once(key)
+                .chain(other_fields(fields1, field1))
+                .chain(other_fields(fields2, field2))
+                .collect(),
+        };
+        write!(out, "{}{terminator}", fields.join(&separator))?;
+        Ok(())
+    };
+    if headers.0.is_some() || headers.1.is_some() {
+        print(headers.0.as_deref(), headers.1.as_deref())?;
+    }
+    let show = |num: u8| {
+        args.unpaired.contains(&num) || args.only_unpaired.contains(&num)
+    };
+
+    let unsorted = merge_walk(
+        groups1,
+        groups2,
+        |a, b| compare(&a.key, &b.key, order.collation, order.insensitive),
+        order,
+        |col| {
+            match col {
+                Col1(group) if show(1) => {
+                    for line in &group.lines {
+                        print(Some(line), None)?;
+                    }
+                }
+                Col2(group) if show(2) => {
+                    for line in &group.lines {
+                        print(None, Some(line))?;
+                    }
+                }
+                // 键相同的两组行两两组合输出
+                Col3(group1, group2) if args.only_unpaired.is_empty() => {
+                    for line1 in &group1.lines {
+                        for line2 in &group2.lines {
+                            print(Some(line1), Some(line2))?;
+                        }
+                    }
+                }
+                _ => (),
+            }
+            Ok(())
+        },
+    )?;
+    out.flush()?;
+
+    if unsorted {
+        bail!("input is not in sorted order");
+    }
+
+    Ok(())
+}
+
+// --------------------------------------------------
+// 把记录拆成字段, 并把连接字段相同的相邻行归为一组
+fn groups(
+    records: impl Iterator<Item = String>,
+    field: usize,
+    delimiter: Option<char>,
+    order: &OrderArgs,
+) -> impl Iterator<Item = Group> {
+    let (collation, insensitive) = (order.collation, order.insensitive);
+    let key = move |fields: &[String]| fields.get(field).cloned().unwrap_or_default();
+    let mut records = records
+        .map(move |line| split_fields(&line, delimiter))
+        .peekable();
+
+    iter::from_fn(move || {
+        let first: Vec<String> = records.next()?;
+        let group_key = key(&first);
+        let mut lines = vec![first];
+        while let Some(line) = records.next_if(|fields| {
+            compare(&key(fields), &group_key, collation, insensitive).is_eq()
+        }) {
+            lines.push(line);
+        }
+        Some(Group { key: group_key, lines })
+    })
+}
+
+// --------------------------------------------------
+// 默认以连续空白分隔字段
+fn split_fields(line: &str, delimiter: Option<char>) -> Vec<String> {
+    match delimiter {
+        Some(delim) => line.split(delim).map(String::from).collect(),
+        None => line.split_whitespace().map(String::from).collect(),
+    }
+}
+
+// --------------------------------------------------
+// 除连接字段外的其余字段
+fn other_fields(
+    fields: Option<&[String]>,
+    skip: usize,
+) -> impl Iterator<Item = &str> {
+    fields
+        .unwrap_or_default()
+        .iter()
+        .enumerate()
+        .filter(move |&(i, _)| i != skip)
+        .map(|(_, f)| f.as_str())
+}
+
+// --------------------------------------------------
+// 解析 -o FORMAT, 字段之间用逗号或空白分隔
+fn parse_format(format: &str) -> Result<Vec<OutField>> {
+    format
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|spec| !spec.is_empty())
+        .map(|spec| {
+            if spec == "0" {
+                return Ok(OutField::Join);
+            }
+            match spec.split_once('.') {
+                Some((num @ ("1" | "2"), idx)) => match idx.parse::<usize>() {
+                    Ok(idx) if idx > 0 => {
+                        Ok(OutField::Field(num.parse()?, idx - 1))
+                    }
+                    _ => bail!(r#"invalid field specifier: "{spec}""#),
+                },
+                _ => bail!(r#"invalid field specifier: "{spec}""#),
+            }
+        })
+        .collect()
+}
@@ -1,12 +1,13 @@
 use crate::Column::*;
-use anyhow::{anyhow, bail, Result}; // 宏anyhow!()转化错误 bail!()包装错误 //Result 类型
-use clap::{ArgAction, Parser};
+use anyhow::{bail, Result}; // bail!()包装错误 //Result 类型
+use clap::{ArgAction, Parser, ValueEnum};
 use std::{
-    cmp::Ordering::*, // 导入 Ordering 枚举的所有变体 (Equal, Less, Greater)
-    fs::File,
-    io::{self, BufRead, BufReader},
+    cmp::Ordering::{self, *}, // 导入 Ordering 枚举的所有变体 (Equal, Less, Greater)
+    io::{BufRead, Write},
 };
 
+mod join;
+
 // cargo add  clap --features derive
 // cargo add anyhow
 
@@ -14,7 +15,9 @@ use std::{
 
 // 显示只在文件1中的行、只在文件2中的行、两个文件共有的行
 #[derive(Debug, Parser)]
-#[command(author, version, about)]
+#[command(author, version, about,
+          after_help = "Run `commr join --help` for joining files on a key field.\n\
+                        To compare a file named join, write it as ./join")]
 /// Rust version of `comm`
 struct Args {
     /// Input file 1
@@ -37,63 +40,87 @@ struct Args {
     #[arg(short('3'), action(ArgAction::SetFalse))]
     show_col3: bool,    // 禁止输出第3列(两个文件共有的行)
 
-    /// Case-insensitive comparison of lines
-    #[arg(short)]
-    insensitive: bool, // 不区分大小写
-
     /// Output delimiter
     #[arg(short, long("output-delimiter"), default_value = "\t")]
     delimiter: String, // 输出分隔符
+
+    /// Output a summary of the column counts
+    #[arg(long)]
+    total: bool, // 最后输出各列的行数
+
+    #[command(flatten)]
+    order: OrderArgs,
+}
+
+// comm 与 join 共用的比较和输入选项
+#[derive(Debug, clap::Args)]
+struct OrderArgs {
+    /// Case-insensitive comparison
+    #[arg(short, long("ignore-case"))]
+    insensitive: bool, // 不区分大小写
+
+    /// Fail if the input is not correctly sorted
+    #[arg(long("check-order"), conflicts_with = "nocheck_order")]
+    check_order: bool, // 发现未排序的行时立即报错退出
+
+    /// Do not check that the input is correctly sorted
+    #[arg(long("nocheck-order"))]
+    nocheck_order: bool, // 不检查排序
+
+    /// Ordering the input files were sorted with
+    #[arg(long, value_enum, default_value_t = Collation::Bytes)]
+    collation: Collation, // 需要与 sort 排序时使用的规则一致
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short('z'), long("zero-terminated"))]
+    zero_terminated: bool, // 输入输出都以 \0 分隔记录
 }
 
-enum Column<'a> { // 生命周期贯穿 枚举Column
-    Col1(&'a str),
-    Col2(&'a str),
-    Col3(&'a str),
+// 比较两行的规则
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Collation {
+    Bytes,      // 按字节比较, 同 LC_ALL=C sort
+    LocaleLike, // 近似 en_US 等语言环境: 先忽略标点和大小写, 小写排在大写前
+    Numeric,    // 按行首数值比较, 同 sort -n
+}
+
+// 归并两个有序输入时, 每一项所属的列
+enum Column<'a, T> { // 生命周期贯穿 枚举Column
+    Col1(&'a T),
+    Col2(&'a T),
+    Col3(&'a T, &'a T), // 两个文件中相等的两项
 }
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
-    }
+    // `commr join ...` 进入 join 模式, 其余情况按 comm 解析参数;
+    // 名为 join 的文件要写成 ./join 才会按 comm 比较
+    let result = if std::env::args().nth(1).as_deref() == Some("join") {
+        join::run(join::Args::parse_from(std::env::args().skip(1)))
+    } else {
+        run(Args::parse())
+    };
+    clr_core::exit(result);
 }
 
 // --------------------------------------------------
 fn run(args: Args) -> Result<()> {
     let file1 = &args.file1;
     let file2 = &args.file2;
+    let order = &args.order;
 
-    // 检查两个文件是否都是 STDIN
-    if file1 == "-" && file2 == "-" {
-        bail!(r#"Both input files cannot be STDIN ("-")"#);
-    }
-
-    // 创建一个闭包函数来处理大小写敏感性
-    // 如果设置了不区分大小写，将行转换为小写，否则保持原样
-    let case = |line: String| {
-        if args.insensitive {
-            line.to_lowercase() // 转换为小写
-        } else {
-            line                // 原样返回
-        }
-    };
-
-    // 打开两个文件并创建行的迭代器
+    // 打开两个文件并创建记录的迭代器
     // open(file1)?: 打开文件，如果出错则返回错误
-    // .lines(): 获取行的迭代器, 不包含 \r\n
-    // .map_while(Result::ok): 过滤掉读取错误，只保留成功读取的行
-    // .map(case): 应用大小写处理闭包,对vec 批量处理
-    let mut lines1 = open(file1)?.lines().map_while(Result::ok).map(case);
-    let mut lines2 = open(file2)?.lines().map_while(Result::ok).map(case);
+    let (lines1, lines2) = open_both(file1, file2, order)?;
+    let terminator = if order.zero_terminated { '\0' } else { '\n' };
+    let mut out = clr_core::stdout();
 
-    let print = |col: Column| {
+    let mut print = |col: Column<String>| -> Result<()> {
         let mut columns = vec![];
         match col { // 匹配对应的类型, 如果这个类型有参数不输出只有当前文件的行,则存储
             Col1(val) => {
                 if args.show_col1 {  // 是否显示第1列 第一个文件单独有的行
-                    columns.push(val);
+                    columns.push(val.as_str());
                 }
             }
             Col2(val) => {
@@ -104,7 +131,7 @@ fn run(args: Args) -> Result<()> {
                     columns.push(val);
                 }
             }
-            Col3(val) => {
+            Col3(val, _) => { // 与 comm 一致, 输出文件1中的原始行
                 if args.show_col3 {  // 禁止比较则两个类型都 push "" 空字符
                     if args.show_col1 {
                         columns.push("");
@@ -119,54 +146,200 @@ fn run(args: Args) -> Result<()> {
 
         // 如果有内容要输出，使用指定的分隔符连接并打印
         if !columns.is_empty() {
-            println!("{}", columns.join(&args.delimiter));
+            write!(out, "{}{terminator}", columns.join(&args.delimiter))?;
         }
+        Ok(())
     };
-    
+
+    let mut counts = [0; 3]; // 每一列的行数
+    // 比较时使用折叠大小写后的键, 但输出保留原始的行
+    let unsorted = merge_walk(
+        lines1,
+        lines2,
+        |a, b| compare(a, b, order.collation, order.insensitive),
+        order,
+        |col| {
+            counts[match col {
+                Col1(_) => 0,
+                Col2(_) => 1,
+                Col3(..) => 2,
+            }] += 1;
+            print(col)
+        },
+    )?;
+
+    if args.total {
+        let [col1, col2, col3] = counts;
+        let d = &args.delimiter;
+        write!(out, "{col1}{d}{col2}{d}{col3}{d}total{terminator}")?;
+    }
+    out.flush()?;
+
+    if unsorted {
+        bail!("input is not in sorted order");
+    }
+
+    Ok(())
+}
+
+// --------------------------------------------------
+// 同时打开两个输入, 返回记录的迭代器
+fn open_both(
+    file1: &str,
+    file2: &str,
+    order: &OrderArgs,
+) -> Result<(Records, Records)> {
+    // 检查两个文件是否都是 STDIN
+    if clr_core::is_stdin(file1) && clr_core::is_stdin(file2) {
+        bail!(r#"Both input files cannot be STDIN ("-")"#);
+    }
+    Ok((
+        records(clr_core::open(file1)?, order.zero_terminated),
+        records(clr_core::open(file2)?, order.zero_terminated),
+    ))
+}
+
+type Records = Box<dyn Iterator<Item = String>>;
+
+// --------------------------------------------------
+// 获取行(或 -z 时以 \0 结尾的记录)的迭代器, 不包含分隔符
+fn records(file: Box<dyn BufRead>, zero_terminated: bool) -> Records {
+    if zero_terminated {
+        Box::new(
+            file.split(b'\0')
+                .map_while(Result::ok) // 过滤掉读取错误，只保留成功读取的记录
+                .map(|record| String::from_utf8_lossy(&record).into_owned()),
+        )
+    } else {
+        // .lines() 不包含 \r\n
+        Box::new(file.lines().map_while(Result::ok))
+    }
+}
+
+// --------------------------------------------------
+// 在两个有序的迭代器上同步前进, 把每一项归入三列之一交给 emit(emit 出错时停止).
+// 默认发现未排序时只警告一次, 返回 true 让调用者最后以错误退出;
+// --check-order 时立即报错
+fn merge_walk<T>(
+    mut lines1: impl Iterator<Item = T>,
+    mut lines2: impl Iterator<Item = T>,
+    compare: impl Fn(&T, &T) -> Ordering,
+    order: &OrderArgs,
+    mut emit: impl FnMut(Column<T>) -> Result<()>,
+) -> Result<bool> {
+    let mut unsorted = [false; 2]; // 两个文件是否已发现未排序
+    // 读取下一项, 并检查它是否排在上一项之前
+    let mut advance = |num: usize,
+                       lines: &mut dyn Iterator<Item = T>,
+                       prev: &Option<T>|
+     -> Result<Option<T>> {
+        let next = lines.next();
+        if let (Some(prev), Some(val)) = (prev, &next)
+            && !order.nocheck_order
+            && compare(val, prev) == Less
+            && !unsorted[num - 1]
+        {
+            if order.check_order {
+                bail!("file {num} is not in sorted order");
+            }
+            clr_core::warn(format!("file {num} is not in sorted order"));
+            unsorted[num - 1] = true;
+        }
+        Ok(next)
+    };
+
     // 获取两个文件的第一行
     let mut line1 = lines1.next();
     let mut line2 = lines2.next();
 
     while line1.is_some() || line2.is_some() {
         match (&line1, &line2) {
-            (Some(val1), Some(val2)) => match val1.cmp(val2) {  // 两者比较
+            (Some(val1), Some(val2)) => match compare(val1, val2) {  // 两者比较
                 Equal => {
                     // 行内容相等:属于第3列(两个文件共有)
-                    print(Col3(val1));
+                    emit(Col3(val1, val2))?;
                     // 两个文件都前进到下一行
-                    line1 = lines1.next();
-                    line2 = lines2.next();
+                    line1 = advance(1, &mut lines1, &line1)?;
+                    line2 = advance(2, &mut lines2, &line2)?;
                 }
                 Less => {
-                    print(Col1(val1));
-                    line1 = lines1.next();
+                    emit(Col1(val1))?;
+                    line1 = advance(1, &mut lines1, &line1)?;
                 }
                 Greater => {
-                    print(Col2(val2));
-                    line2 = lines2.next();
+                    emit(Col2(val2))?;
+                    line2 = advance(2, &mut lines2, &line2)?;
                 }
             },
             (Some(val1), None) => {
-                print(Col1(val1));
-                line1 = lines1.next();
+                emit(Col1(val1))?;
+                line1 = advance(1, &mut lines1, &line1)?;
             }
             (None, Some(val2)) => {
-                print(Col2(val2));
-                line2 = lines2.next();
+                emit(Col2(val2))?;
+                line2 = advance(2, &mut lines2, &line2)?;
             }
             _ => (),
         }
     }
 
-    Ok(())
+    Ok(unsorted.contains(&true))
+}
+
+// --------------------------------------------------
+fn compare(a: &str, b: &str, collation: Collation, insensitive: bool) -> Ordering {
+    // 逐字符比较, 不区分大小写时转换为小写, 不分配新的字符串
+    let fold = || {
+        if insensitive {
+            let lower_b = b.chars().flat_map(char::to_lowercase);
+            a.chars().flat_map(char::to_lowercase).cmp(lower_b)
+        } else {
+            a.cmp(b)
+        }
+    };
+
+    match collation {
+        Collation::Bytes => fold(),
+        Collation::LocaleLike => {
+            // 第一层: 只比较字母数字, 忽略大小写
+            let key = |s: &str| -> String {
+                s.chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .flat_map(char::to_lowercase)
+                    .collect()
+            };
+            // 第二层: 小写排在大写前面; 最后按字节比较保证结果稳定
+            let case = |s: &str| -> Vec<bool> {
+                s.chars().filter(|c| c.is_alphanumeric()).map(char::is_uppercase).collect()
+            };
+            key(a).cmp(&key(b)).then_with(|| {
+                if insensitive {
+                    fold()
+                } else {
+                    case(a).cmp(&case(b)).then_with(|| a.cmp(b))
+                }
+            })
+        }
+        Collation::Numeric => numeric_prefix(a)
+            .total_cmp(&numeric_prefix(b))
+            .then_with(fold), // 数值相同时按字节比较
+    }
 }
 
 // --------------------------------------------------
-fn open(filename: &str) -> Result<Box<dyn BufRead>> {
-    match filename {
-        "-" => Ok(Box::new(BufReader::new(io::stdin()))),
-        _ => Ok(Box::new(BufReader::new(
-            File::open(filename).map_err(|e| anyhow!("{filename}: {e}"))?,
-        ))),
+// 解析行首的数字(允许前导空白、负号和小数), 没有数字时视为 0
+fn numeric_prefix(line: &str) -> f64 {
+    let line = line.trim_start();
+    let digits = line
+        .char_indices()
+        .take_while(|&(i, c)| c.is_ascii_digit() || c == '.' || (i == 0 && c == '-'))
+        .count();
+    let mut prefix = &line[..digits];
+    // "1.2.3" 只取 "1.2"
+    if let Some((int, rest)) = prefix.split_once('.')
+        && let Some(dot) = rest.find('.')
+    {
+        prefix = &prefix[..int.len() + 1 + dot];
     }
+    prefix.parse().unwrap_or(0.0)
 }
@@ -2,7 +2,7 @@ use anyhow::Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
-use rand::{distributions::Alphanumeric, Rng};
+use rand::{distr::Alphanumeric, Rng};
 use std::fs;
 
 const PRG: &str = "commr";
@@ -24,7 +24,7 @@ fn dies_no_args() -> Result<()> {
 // --------------------------------------------------
 fn gen_bad_file() -> String {
     loop {
-        let filename: String = rand::thread_rng()
+        let filename: String = rand::rng()
             .sample_iter(&Alphanumeric)
             .take(7)
             .map(char::from)
@@ -342,3 +342,203 @@ fn file1_file2_123_delim() -> Result<()> {
 fn blank_file1() -> Result<()> {
     run(&[BLANK, FILE1], "tests/expected/blank_file1.out")
 }
+
+// --------------------------------------------------
+// 在临时目录中写入一个输入文件, 返回其路径
+fn gen_file(name: &str, contents: &str) -> Result<String> {
+    let dir = std::env::temp_dir().join(format!("commr-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name);
+    fs::write(&path, contents)?;
+    Ok(path.display().to_string())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_warns() -> Result<()> {
+    let file1 = gen_file("unsorted_warns1.txt", "b\na\nc\n")?;
+    let file2 = gen_file("unsorted_warns2.txt", "a\nc\n")?;
+    Command::cargo_bin(PRG)?
+        .args([&file1, &file2])
+        .assert()
+        .failure()
+        .stdout("\ta\nb\na\n\t\tc\n")
+        .stderr(
+            "commr: file 1 is not in sorted order\n\
+             commr: input is not in sorted order\n",
+        );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_check_order() -> Result<()> {
+    let file1 = gen_file("check_order1.txt", "a\nc\n")?;
+    let file2 = gen_file("check_order2.txt", "a\nz\nb\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["--check-order", &file1, &file2])
+        .assert()
+        .failure()
+        .stdout("\t\ta\nc\n\tz\n")
+        .stderr("commr: file 2 is not in sorted order\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unsorted_nocheck_order() -> Result<()> {
+    let file1 = gen_file("nocheck_order1.txt", "b\na\n")?;
+    let file2 = gen_file("nocheck_order2.txt", "a\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["--nocheck-order", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("\ta\nb\na\n")
+        .stderr("");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn total() -> Result<()> {
+    let file1 = gen_file("total1.txt", "a\nb\nc\nd\n")?;
+    let file2 = gen_file("total2.txt", "b\nd\ne\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["--total", "-12", "-d", ",", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("b\nd\n2,1,2,total\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn insensitive_keeps_case() -> Result<()> {
+    let file1 = gen_file("keeps_case1.txt", "Apple\nbanana\nCherry\n")?;
+    let file2 = gen_file("keeps_case2.txt", "apple\nBanana\ndate\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["-i", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("\t\tApple\n\t\tbanana\nCherry\n\tdate\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn collation_numeric() -> Result<()> {
+    let file1 = gen_file("numeric1.txt", "2\n10\n33 x\n")?;
+    let file2 = gen_file("numeric2.txt", "-1\n3\n10\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["--collation=numeric", "--check-order", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("\t-1\n2\n\t3\n\t\t10\n33 x\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn collation_locale_like() -> Result<()> {
+    let file1 = gen_file("locale1.txt", "a-b\nB\nc\n")?;
+    let file2 = gen_file("locale2.txt", "ab\nb\nC\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["--collation", "locale-like", "--check-order", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("a-b\n\tab\n\tb\nB\nc\n\tC\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    let file1 = gen_file("zero1.txt", "a\0b\nc\0")?;
+    let file2 = gen_file("zero2.txt", "b\nc\0d\0")?;
+    Command::cargo_bin(PRG)?
+        .args(["-z", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("a\0\t\tb\nc\0\td\0");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn join_csv() -> Result<()> {
+    let file1 = gen_file("join1.csv", "id,name\n1,ann\n2,bob\n2,bea\n4,dan\n")?;
+    let file2 = gen_file("join2.csv", "id,amt\n1,10\n2,20\n3,30\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["join", "--header", "-t", ",", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("id,name,amt\n1,ann,10\n2,bob,20\n2,bea,20\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["join", "--header", "-t,", "-a1", "-a", "2", "-o", "0,1.2,2.2"])
+        .args(["-e", "NA", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("id,name,amt\n1,ann,10\n2,bob,20\n2,bea,20\n3,NA,30\n4,dan,NA\n");
+
+    Command::cargo_bin(PRG)?
+        .args(["join", "--header", "-t,", "-v", "1", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("id,name,amt\n4,dan\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn file_named_join() -> Result<()> {
+    // 第一个参数是 join 时进入 join 模式, 写成 ./join 时是普通文件
+    let file = gen_file("join", "a\nb\n")?;
+    let dir = std::path::Path::new(&file).parent().unwrap();
+    gen_file("join_other", "b\n")?;
+    Command::cargo_bin(PRG)?
+        .current_dir(dir)
+        .args(["./join", "join_other"])
+        .assert()
+        .success()
+        .stdout("a\n\t\tb\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn join_fields() -> Result<()> {
+    let file1 = gen_file("join_fields1.txt", "x  A  1\ny b 2\n")?;
+    let file2 = gen_file("join_fields2.txt", "a X\nb Y\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["join", "-i", "-1", "2", &file1, &file2])
+        .assert()
+        .success()
+        .stdout("A x 1 X\nb y 2 Y\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn join_unsorted() -> Result<()> {
+    let file1 = gen_file("join_unsorted1.txt", "b 1\na 2\n")?;
+    let file2 = gen_file("join_unsorted2.txt", "a 3\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["join", "--check-order", &file1, &file2])
+        .assert()
+        .failure()
+        .stderr("commr: file 1 is not in sorted order\n");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_join_bad_format() -> Result<()> {
+    let file1 = gen_file("join_bad1.txt", "a 1\n")?;
+    Command::cargo_bin(PRG)?
+        .args(["join", "-o", "0,3.1", &file1, &file1])
+        .assert()
+        .failure()
+        .stderr(r#"commr: invalid field specifier: "3.1""#.to_string() + "\n");
+    Ok(())
+}
@@ -0,0 +1,192 @@
+// -f/-F 跟随模式: 打印完末尾后继续输出文件新增的内容
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::unix::fs::MetadataExt,
+    path::Path,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum FollowMode {
+    Descriptor, // 跟随已打开的文件, 文件被改名后仍然读取它
+    Name,       // 跟随文件名, 日志轮转后重新打开同名的新文件
+}
+
+#[derive(Debug)]
+pub struct Options {
+    pub mode: FollowMode,
+    pub retry: bool,           // 文件不可访问时继续尝试打开
+    pub pid: Option<i32>,      // 该进程退出后停止
+    pub interval: Duration,    // 两次检查之间的间隔
+    pub headers: bool,         // 切换到另一个文件时输出 ==> name <==
+}
+
+// 正在跟随的文件
+#[derive(Debug)]
+pub struct Followed {
+    pub name: String,
+    pub file: Option<File>, // None 表示当前不可访问
+    pub pos: u64,           // 已经输出到的位置
+}
+
+// --------------------------------------------------
+// last 是最后一个输出过内容的文件下标, 用来决定是否要输出文件名头
+pub fn follow(
+    mut files: Vec<Followed>,
+    opts: &Options,
+    mut last: Option<usize>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let (tx, rx) = mpsc::channel();
+    // 有 inotify 等通知机制时等待文件变化, 否则退回到定时轮询
+    let watcher = watch(&files, tx);
+
+    loop {
+        // 先检查进程, 保证进程退出前写入的内容也能被输出
+        let alive = opts.pid.is_none_or(process_alive);
+
+        for (idx, followed) in files.iter_mut().enumerate() {
+            if opts.mode == FollowMode::Name {
+                reopen(followed, idx, opts, &mut last, out)?;
+            }
+            print_new(followed, idx, opts.headers, &mut last, out)?;
+        }
+
+        if !alive {
+            return Ok(());
+        }
+        if !opts.retry && files.iter().all(|f| f.file.is_none()) {
+            bail!("no files remaining");
+        }
+
+        match &watcher {
+            Some(_) => {
+                // 超时后也要检查一次, 以便发现 --pid 进程退出
+                if rx.recv_timeout(opts.interval).is_ok() {
+                    rx.try_iter().for_each(drop);
+                }
+            }
+            None => thread::sleep(opts.interval),
+        }
+    }
+}
+
+// --------------------------------------------------
+// 监视文件所在的目录, 这样文件被删除或重新创建时也能收到通知
+fn watch(
+    files: &[Followed],
+    tx: mpsc::Sender<notify::Result<notify::Event>>,
+) -> Option<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(tx).ok()?;
+    let dirs: HashSet<_> = files
+        .iter()
+        .map(|f| match Path::new(&f.name).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => Path::new(".").to_path_buf(),
+        })
+        .collect();
+
+    let mut watching = false;
+    for dir in dirs {
+        watching |= watcher.watch(&dir, RecursiveMode::NonRecursive).is_ok();
+    }
+    watching.then_some(watcher)
+}
+
+// --------------------------------------------------
+// 按文件名跟随时, 检查文件是否消失、被替换或重新出现
+fn reopen(
+    followed: &mut Followed,
+    idx: usize,
+    opts: &Options,
+    last: &mut Option<usize>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let name = followed.name.clone();
+    let current = match fs::metadata(&name) {
+        Err(e) => {
+            if followed.file.take().is_some() {
+                clr_core::notice(format!(
+                    "'{name}' has become inaccessible: {e}"
+                ));
+            }
+            return Ok(());
+        }
+        Ok(metadata) => metadata,
+    };
+
+    let same_file = followed
+        .file
+        .as_ref()
+        .and_then(|file| file.metadata().ok())
+        .is_some_and(|m| (m.dev(), m.ino()) == (current.dev(), current.ino()));
+    // 没有 --retry 时, 文件消失后不再重新打开
+    if same_file || (followed.file.is_none() && !opts.retry) {
+        return Ok(());
+    }
+
+    if let Ok(file) = File::open(&name) {
+        if followed.file.is_some() {
+            // 先输出旧文件中剩余的内容
+            print_new(followed, idx, opts.headers, last, out)?;
+            clr_core::notice(format!(
+                "'{name}' has been replaced; following new file"
+            ));
+        } else {
+            clr_core::notice(format!(
+                "'{name}' has appeared; following new file"
+            ));
+        }
+        followed.file = Some(file);
+        followed.pos = 0;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+fn print_new(
+    followed: &mut Followed,
+    idx: usize,
+    headers: bool,
+    last: &mut Option<usize>,
+    out: &mut impl Write,
+) -> Result<()> {
+    let Some(file) = &mut followed.file else {
+        return Ok(());
+    };
+
+    let len = file.metadata()?.len();
+    if len < followed.pos {
+        clr_core::notice(format!("{}: file truncated", followed.name));
+        followed.pos = 0;
+    }
+    if len == followed.pos {
+        return Ok(());
+    }
+
+    file.seek(SeekFrom::Start(followed.pos))?;
+    let mut buffer = Vec::new();
+    followed.pos += file.read_to_end(&mut buffer)? as u64;
+
+    if headers && *last != Some(idx) {
+        writeln!(out, "\n==> {} <==", followed.name)?;
+    }
+    *last = Some(idx);
+    write!(out, "{}", String::from_utf8_lossy(&buffer))?; // 转string ,处理非UTF-8内容
+    out.flush()?; // 最后一行可能还没有换行符
+    Ok(())
+}
+
+// --------------------------------------------------
+// kill(pid, 0) 不发送信号, 只检查进程是否存在
+fn process_alive(pid: i32) -> bool {
+    let ret = unsafe { libc::kill(pid, 0) };
+    ret == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
@@ -4,12 +4,17 @@ use clap::Parser;
 use once_cell::sync::OnceCell;  // once_cell::OnceCell:用于一次性初始化的静态变量
 use regex::Regex;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Read, Seek, SeekFrom},  // Seek,处理光标位置, SeekFrom 移动光标
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},  // Seek,处理光标位置, SeekFrom 移动光标
+    time::Duration,
 };
 
+mod follow;
+use follow::{FollowMode, Followed};
+
 // cargo add  clap --features derive
 // cargo add anyhow once_cell regex
+// cargo add notify libc
 
 // cargo add --dev assert_cmd predicates pretty_assertions rand sys_info
 
@@ -19,12 +24,12 @@ use std::{
 /// Rust version of `tail`
 struct Args {
     /// Input file(s)
-    #[arg(required = true)]
+    #[arg(default_value = "-", value_name = "FILE")]
     files: Vec<String>,
 
     /// Number of lines
     #[arg(value_name = "LINES", short('n'), long, default_value = "10")]
-    lines: String, // 注意: 这里类型是 String 而不是数字，为了支持 +N 语法
+    lines: String, // 注意: 这里类型是 String 而不是数字，为了支持 +N 语法和 10K 等后缀
 
     /// Number of bytes
     #[arg(value_name = "BYTES", short('c'), long, conflicts_with("lines"))]
@@ -33,6 +38,37 @@ struct Args {
     /// Suppress headers
     #[arg(short, long)]
     quiet: bool,
+
+    /// Always print headers
+    #[arg(short, long, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Line delimiter is NUL, not newline
+    #[arg(short('z'), long("zero-terminated"))]
+    zero_terminated: bool,
+
+    /// Output appended data as the file grows
+    #[arg(short('f'), long, value_name = "HOW", value_enum,
+          num_args = 0..=1, require_equals = true,
+          default_missing_value = "descriptor")]
+    follow: Option<FollowMode>,
+
+    /// Same as --follow=name --retry
+    #[arg(short('F'), conflicts_with = "follow")]
+    follow_retry: bool,
+
+    /// Keep trying to open a file if it is inaccessible
+    #[arg(long)]
+    retry: bool,
+
+    /// With -f, terminate after process PID dies
+    #[arg(long, value_name = "PID")]
+    pid: Option<i32>,
+
+    /// With -f, sleep for about SECONDS between checks
+    #[arg(short('s'), long("sleep-interval"), value_name = "SECONDS",
+          default_value = "1.0")]
+    sleep_interval: f64,
 }
 
 static NUM_RE: OnceCell<Regex> = OnceCell::new();
@@ -45,10 +81,7 @@ enum TakeValue {
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
-    }
+    clr_core::exit(run(Args::parse()));
 }
 
 // --------------------------------------------------
@@ -62,40 +95,114 @@ fn run(args: Args) -> Result<()> {
         .transpose()    // 将 Result<Option> 转换为 Option<Result>
         .map_err(|e| anyhow!("illegal byte count -- {e}"))?;
 
+    let interval = Duration::try_from_secs_f64(args.sleep_interval)
+        .map_err(|_| anyhow!("invalid number of seconds: {}", args.sleep_interval))?;
+    // -F 等同于 --follow=name --retry
+    let follow_mode = if args.follow_retry {
+        Some(FollowMode::Name)
+    } else {
+        args.follow
+    };
+    let retry = args.retry || args.follow_retry;
+
     let num_files = args.files.len();
+    let headers = args.verbose || (!args.quiet && num_files > 1);
+    let delimiter = if args.zero_terminated { b'\0' } else { b'\n' };
+    let mut followed = vec![];
+    let mut last = None; // 最后输出内容的文件下标
+    let mut out = clr_core::stdout();
     for (file_num, filename) in args.files.iter().enumerate() {
-        match File::open(filename) {
-            Err(err) => eprintln!("{filename}: {err}"),
-            Ok(file) => {
+        match clr_core::open_file(filename) {
+            Err(err) => {
+                clr_core::warn(err);
+                if follow_mode.is_some() && retry { // 之后文件出现时再开始跟随
+                    followed.push(Followed {
+                        name: filename.to_string(),
+                        file: None,
+                        pos: 0,
+                    });
+                }
+            }
+            Ok(mut file) => {
                 // 多文件时显示文件名头(除非 quiet 模式)
-                if !args.quiet && num_files > 1 {
-                    println!(
-                        "{}==> {filename} <==",
+                if headers {
+                    writeln!(
+                        out,
+                        "{}==> {} <==",
                         if file_num > 0 { "\n" } else { "" },
-                    );
+                        display_name(filename),
+                    )?;
                 }
-                // 获取文件总行数和字节数
-                let (total_lines, total_bytes) = count_lines_bytes(filename)?;
-                let file = BufReader::new(file);
 
+                // 普通文件可以从末尾向前查找, 管道等只能顺序读取一遍
+                let regular = file.metadata()?.is_file();
                 // 根据参数选择输出方式 #bytes 是 Option<TakeValue> 类型, Option<Some,None> match Some 或者None
-                if let Some(num_bytes) = &bytes {
-                    print_bytes(file, num_bytes, total_bytes)?;
-                } else {
-                    print_lines(file, &lines, total_lines)?;
+                match (&bytes, &lines) {
+                    (Some(TakeNum(num)), _) if regular && *num < 0 => {
+                        let total = file.seek(SeekFrom::End(0))?;
+                        let start = total.saturating_sub(num.unsigned_abs());
+                        copy_from(&mut file, start, &mut out)?;
+                    }
+                    (Some(num_bytes), _) => print_bytes(&mut file, num_bytes, &mut out)?,
+                    (None, TakeNum(num)) if regular && *num < 0 => {
+                        let start =
+                            find_last_lines(&mut file, num.unsigned_abs(), delimiter)?;
+                        copy_from(&mut file, start, &mut out)?;
+                    }
+                    (None, num_lines) => print_lines(
+                        BufReader::new(&mut file),
+                        num_lines,
+                        delimiter,
+                        &mut out,
+                    )?,
+                }
+
+                // 从文件末尾开始跟随; 标准输入没有文件名, 只能按描述符跟随
+                let can_follow = regular
+                    && (filename != "-" || follow_mode == Some(FollowMode::Descriptor));
+                if follow_mode.is_some() && can_follow {
+                    let pos = file.seek(SeekFrom::End(0))?;
+                    followed.push(Followed {
+                        name: display_name(filename).to_string(),
+                        file: Some(file),
+                        pos,
+                    });
+                    last = Some(followed.len() - 1);
                 }
             }
         }
     }
 
+    out.flush()?; // 跟随之前先输出已有的末尾
+    match follow_mode {
+        // 像 GNU tail 一样, 输入只有管道时忽略 -f
+        Some(_) if followed.is_empty() => {}
+        Some(mode) => {
+            let opts = follow::Options {
+                mode,
+                retry,
+                pid: args.pid,
+                interval,
+                headers,
+            };
+            follow::follow(followed, &opts, last, &mut out)?;
+        }
+        None => {}
+    }
+
     Ok(())
 }
 
+// --------------------------------------------------
+fn display_name(filename: &str) -> &str {
+    if filename == "-" { "standard input" } else { filename }
+}
+
 // --------------------------------------------------
 fn parse_num(val: String) -> Result<TakeValue> {
     // 延迟初始化正则表达式(只编译一次)
-    let num_re =
-        NUM_RE.get_or_init(|| Regex::new(r"^([+-])?(\d+)$").unwrap());
+    let num_re = NUM_RE
+        .get_or_init(|| Regex::new(r"^([+-])?(\d+)([a-zA-Z]*)$").unwrap());
 
     match num_re.captures(&val) {
         Some(caps) => {
@@ -103,8 +210,10 @@ fn parse_num(val: String) -> Result<TakeValue> {
             let sign = caps.get(1).map_or("-", |m| m.as_str()); // Option<Match>.map_or(默认值,Some(Value).as_str())
             let signed_num =
                 format!("{sign}{}", caps.get(2).unwrap().as_str());
+            let multiplier = parse_suffix(caps.get(3).unwrap().as_str());
 
-            if let Ok(num) = signed_num.parse() {
+            if let (Ok(num), Some(multiplier)) = (signed_num.parse::<i64>(), multiplier) {
+                let num = num.saturating_mul(multiplier); // 与 GNU 一样, 过大的值取最大值
                 if sign == "+" && num == 0 {
                     Ok(PlusZero)     // 返回值 Ok()+0)
                 } else {
@@ -118,6 +227,24 @@ fn parse_num(val: String) -> Result<TakeValue> {
     }
 }
 
+// --------------------------------------------------
+// GNU 的单位后缀: b=512, kB=1000, K=KiB=1024, MB=1000*1000, M=MiB=1024*1024, ...
+fn parse_suffix(suffix: &str) -> Option<i64> {
+    match suffix {
+        "" => return Some(1),
+        "b" => return Some(512),
+        _ => (),
+    }
+    let (unit, rest) = suffix.split_at(1);
+    let power = "KMGTPE".find(&unit.replace('k', "K"))? as u32 + 1;
+    let base: i64 = match rest {
+        "" | "iB" => 1024,
+        "B" => 1000,
+        _ => return None,
+    };
+    base.checked_pow(power)
+}
+
 // --------------------------------------------------
 // We have to specify the type and assign to a variable here because
 // &['+', '-'] has the type &[char; 2], and we want to coerce it to
@@ -146,146 +273,139 @@ fn parse_num(val: String) -> Result<TakeValue> {
 //}
 
 // --------------------------------------------------
-fn count_lines_bytes(filename: &str) -> Result<(i64, i64)> {
-    let mut file = BufReader::new(File::open(filename)?);
-    let mut num_lines = 0;
-    let mut num_bytes = 0;
-    let mut buf = Vec::new();
-    loop {
-        let bytes_read = file.read_until(b'\n', &mut buf)?; // read_until,按照 \n 换行符为分割,返回读取的字节数,字节存储到buf
-        if bytes_read == 0 { // EOF
-            break;
+// 从末尾向前按块读取, 找到倒数第 num 行的起始位置, 不需要读完整个文件
+fn find_last_lines<T: Read + Seek>(
+    file: &mut T,
+    num: u64,
+    delimiter: u8,
+) -> Result<u64> {
+    const BLOCK_SIZE: u64 = 64 * 1024;
+    let total = file.seek(SeekFrom::End(0))?;
+    let mut buffer = vec![0; BLOCK_SIZE as usize];
+    let mut end = total;
+    let mut found = 0;
+
+    while end > 0 {
+        let start = end.saturating_sub(BLOCK_SIZE);
+        let block = &mut buffer[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?; // 光标跳转
+        file.read_exact(block)?;
+
+        for (i, byte) in block.iter().enumerate().rev() {
+            let pos = start + i as u64;
+            // 文件末尾的换行符属于最后一行, 不算行的分隔
+            if *byte == delimiter && pos + 1 != total {
+                found += 1;
+                if found == num {
+                    return Ok(pos + 1);
+                }
+            }
         }
-        num_lines += 1;
-        num_bytes += bytes_read as i64;
-        buf.clear();
+        end = start;
     }
-    Ok((num_lines, num_bytes))
+
+    Ok(0) // 行数不足 num, 输出整个文件
 }
 
 // --------------------------------------------------
-fn print_bytes<T: Read + Seek>( // 实现了 Read + Seek trait 的泛型 T
-    mut file: T,
-    num_bytes: &TakeValue,
-    total_bytes: i64,
+fn copy_from<T: Read + Seek>(
+    file: &mut T,
+    start: u64,
+    out: &mut impl Write,
 ) -> Result<()> {
-    if let Some(start) = get_start_index(num_bytes, total_bytes) {
-        file.seek(SeekFrom::Start(start))?; // 光标跳转
-        let mut buffer = Vec::new();
-        file.read_to_end(&mut buffer)?; // 读取到末尾,存储给buffer
-        if !buffer.is_empty() {
-            print!("{}", String::from_utf8_lossy(&buffer)); // 转string ,处理非UTF-8内容
-        }
-    }
-
+    file.seek(SeekFrom::Start(start))?;
+    io::copy(file, out)?;
     Ok(())
 }
 
 // --------------------------------------------------
-fn print_lines(
-    mut file: impl BufRead,
-    num_lines: &TakeValue,
-    total_lines: i64,
+// 顺序读取一遍输入; 取末尾若干字节时只保留最后 num 个字节
+fn print_bytes(
+    mut file: impl Read,
+    num_bytes: &TakeValue,
+    out: &mut impl Write,
 ) -> Result<()> {
-    if let Some(start) = get_start_index(num_lines, total_lines) {
-        let mut line_num = 0;
-        let mut buf = Vec::new();
-        loop {
-            let bytes_read = file.read_until(b'\n', &mut buf)?; // read_until,按照 \n 换行符为分割,返回读取的字节数,字节存储到buf
-            if bytes_read == 0 {
-                break;
-            }
-            if line_num >= start {
-                print!("{}", String::from_utf8_lossy(&buf));
+    match num_bytes {
+        PlusZero => {
+            io::copy(&mut file, out)?;
+        }
+        TakeNum(0) => (),
+        TakeNum(num) if *num > 0 => { // 跳过前 num - 1 个字节
+            io::copy(&mut file.by_ref().take(*num as u64 - 1), &mut io::sink())?;
+            io::copy(&mut file, out)?;
+        }
+        TakeNum(num) => {
+            let num = num.unsigned_abs() as usize;
+            let mut ring = VecDeque::new();
+            let mut buffer = [0; 8192];
+            loop {
+                let bytes_read = file.read(&mut buffer)?;
+                if bytes_read == 0 { // EOF
+                    break;
+                }
+                ring.extend(&buffer[..bytes_read]);
+                if ring.len() > num {
+                    ring.drain(..ring.len() - num);
+                }
             }
-            line_num += 1;
-            buf.clear();
+            let (front, back) = ring.as_slices();
+            out.write_all(front)?;
+            out.write_all(back)?;
         }
     }
-
     Ok(())
 }
 
 // --------------------------------------------------
-fn get_start_index(take_val: &TakeValue, total: i64) -> Option<u64> {
-    match take_val {
+// 顺序读取一遍输入; 取末尾若干行时只保留最后 num 行
+fn print_lines(
+    mut file: impl BufRead,
+    num_lines: &TakeValue,
+    delimiter: u8,
+    out: &mut impl Write,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    match num_lines {
         PlusZero => {
-            if total > 0 {
-                Some(0)
-            } else {
-                None
+            io::copy(&mut file, out)?;
+        }
+        TakeNum(0) => (),
+        TakeNum(num) if *num > 0 => { // 跳过前 num - 1 行
+            for _ in 1..*num {
+                // read_until,按照 \n 换行符(-z 时为 \0)为分割,返回读取的字节数,字节存储到buf
+                if file.read_until(delimiter, &mut buf)? == 0 {
+                    break;
+                }
+                buf.clear();
             }
+            io::copy(&mut file, out)?;
         }
         TakeNum(num) => {
-            // num 的类型是&i64 ,对应数字引用 &0 0数字的引用地址
-            if num == &0 || total == 0 || num > &total {
-                None
-            } else {
-                let start = if num < &0 { total + num } else { num - 1 };
-                Some(if start < 0 { 0 } else { start as u64 })
+            let num = num.unsigned_abs();
+            let mut ring: VecDeque<Vec<u8>> = VecDeque::new();
+            while file.read_until(delimiter, &mut buf)? > 0 {
+                if ring.len() as u64 == num { // 复用最早一行的缓冲区
+                    let mut oldest = ring.pop_front().unwrap_or_default();
+                    oldest.clear();
+                    ring.push_back(std::mem::replace(&mut buf, oldest));
+                } else {
+                    ring.push_back(std::mem::take(&mut buf));
+                }
+            }
+            for line in &ring {
+                out.write_all(line)?;
             }
         }
     }
+    Ok(())
 }
 
 // --------------------------------------------------
 // #[cfg(test)]
 // mod tests {
-//     use super::{
-//         count_lines_bytes, get_start_index, parse_num, TakeValue::*,
-//     };
+//     use super::{parse_num, TakeValue::*};
 //     use pretty_assertions::assert_eq;
 
-//     #[test]
-//     fn test_count_lines_bytes() {
-//         let res = count_lines_bytes("tests/inputs/one.txt");
-//         assert!(res.is_ok());
-//         let (lines, bytes) = res.unwrap();
-//         assert_eq!(lines, 1);
-//         assert_eq!(bytes, 24);
-
-//         let res = count_lines_bytes("tests/inputs/twelve.txt");
-//         assert!(res.is_ok());
-//         let (lines, bytes) = res.unwrap();
-//         assert_eq!(lines, 12);
-//         assert_eq!(bytes, 63);
-//     }
-
-//     #[test]
-//     fn test_get_start_index() {
-//         // +0 from an empty file (0 lines/bytes) returns None
-//         assert_eq!(get_start_index(&PlusZero, 0), None);
-
-//         // +0 from a nonempty file returns an index that
-//         // is one less than the number of lines/bytes
-//         assert_eq!(get_start_index(&PlusZero, 1), Some(0));
-
-//         // Taking 0 lines/bytes returns None
-//         assert_eq!(get_start_index(&TakeNum(0), 1), None);
-
-//         // Taking any lines/bytes from an empty file returns None
-//         assert_eq!(get_start_index(&TakeNum(1), 0), None);
-
-//         // Taking more lines/bytes than is available returns None
-//         assert_eq!(get_start_index(&TakeNum(2), 1), None);
-
-//         // When starting line/byte is less than total lines/bytes,
-//         // return one less than starting number
-//         assert_eq!(get_start_index(&TakeNum(1), 10), Some(0));
-//         assert_eq!(get_start_index(&TakeNum(2), 10), Some(1));
-//         assert_eq!(get_start_index(&TakeNum(3), 10), Some(2));
-
-//         // When starting line/byte is negative and less than total,
-//         // return total - start
-//         assert_eq!(get_start_index(&TakeNum(-1), 10), Some(9));
-//         assert_eq!(get_start_index(&TakeNum(-2), 10), Some(8));
-//         assert_eq!(get_start_index(&TakeNum(-3), 10), Some(7));
-
-//         // When the starting line/byte is negative and more than the total,
-//         // return 0 to print the whole file
-//         assert_eq!(get_start_index(&TakeNum(-20), 10), Some(0));
-//     }
-
 //     #[test]
 //     fn test_parse_num() {
 //         // All integers should be interpreted as negative numbers
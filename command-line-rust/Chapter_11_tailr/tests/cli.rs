@@ -2,7 +2,7 @@ use anyhow::Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
-use rand::{distributions::Alphanumeric, Rng};
+use rand::{distr::Alphanumeric, Rng};
 use std::fs::{self, File};
 use std::io::Read;
 
@@ -15,7 +15,7 @@ const TWELVE: &str = "tests/inputs/twelve.txt";
 
 // --------------------------------------------------
 fn random_string() -> String {
-    rand::thread_rng()
+    rand::rng()
         .sample_iter(&Alphanumeric)
         .take(7)
         .map(char::from)
@@ -34,11 +34,13 @@ fn gen_bad_file() -> String {
 
 // --------------------------------------------------
 #[test]
-fn dies_no_args() -> Result<()> {
+fn no_args_reads_stdin() -> Result<()> {
     Command::cargo_bin(PRG)?
+        .args(["-n", "2"])
+        .write_stdin("a\nb\nc\n")
         .assert()
-        .failure()
-        .stderr(predicate::str::contains("Usage"));
+        .success()
+        .stdout("b\nc\n");
 
     Ok(())
 }
@@ -94,6 +96,7 @@ fn skips_bad_file() -> Result<()> {
     Command::cargo_bin(PRG)?
         .args([ONE, &bad, TWO])
         .assert()
+        .code(1)
         .stderr(predicate::str::is_match(expected)?);
 
     Ok(())
@@ -828,3 +831,190 @@ fn multiple_files_c_plus_3() -> Result<()> {
         "tests/expected/all.c+3.out",
     )
 }
+
+// --------------------------------------------------
+#[test]
+fn follow_until_pid_exits() -> Result<()> {
+    use std::{io::Write, thread, time::Duration};
+
+    let dir = std::env::temp_dir().join(format!("tailr-f-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let (log1, log2) = (dir.join("one.log"), dir.join("two.log"));
+    fs::write(&log1, "a\nb\n")?;
+    fs::write(&log2, "c\n")?;
+
+    // tailr 在这个进程退出后停止跟随, 由单独的线程回收以免留下僵尸进程
+    let mut writer = std::process::Command::new("sleep").arg("1").spawn()?;
+    let pid = writer.id().to_string();
+    let reaper = thread::spawn(move || writer.wait());
+    let appender = {
+        let log1 = log1.clone();
+        thread::spawn(move || -> std::io::Result<()> {
+            thread::sleep(Duration::from_millis(400));
+            fs::OpenOptions::new().append(true).open(&log1)?.write_all(b"d\n")
+        })
+    };
+
+    let (name1, name2) = (log1.display().to_string(), log2.display().to_string());
+    let output = Command::cargo_bin(PRG)?
+        .args(["-f", "-n", "1", "-s", "0.1", "--pid"])
+        .arg(pid)
+        .args([&name1, &name2])
+        .timeout(Duration::from_secs(10))
+        .output()
+        .expect("fail");
+    reaper.join().unwrap()?;
+    appender.join().unwrap()?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("==> {name1} <==\nb\n\n==> {name2} <==\nc\n\n==> {name1} <==\nd\n")
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn fifo_last_lines_and_bytes() -> Result<()> {
+    use std::{io::Write, thread};
+
+    let dir = std::env::temp_dir().join(format!("tailr-fifo-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let input: String = (1..=1000).map(|n| format!("{n}\n")).collect();
+
+    for (args, expected) in [
+        (["-n", "3"], "998\n999\n1000\n"),
+        (["-c", "6"], "\n1000\n"),
+        (["-n", "+999"], "999\n1000\n"),
+    ] {
+        // 命名管道不能 seek, 只能用有界缓冲区读取一遍
+        let fifo = dir.join("pipe");
+        let _ = fs::remove_file(&fifo);
+        assert!(std::process::Command::new("mkfifo").arg(&fifo).status()?.success());
+        let writer = {
+            let (fifo, input) = (fifo.clone(), input.clone());
+            thread::spawn(move || -> std::io::Result<()> {
+                File::create(&fifo)?.write_all(input.as_bytes())
+            })
+        };
+
+        Command::cargo_bin(PRG)?
+            .args(args)
+            .arg(&fifo)
+            .assert()
+            .success()
+            .stdout(expected);
+        writer.join().unwrap()?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn stdin_with_headers() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-v", "-n", "+2", "-"])
+        .write_stdin("a\nb\nc\n")
+        .assert()
+        .success()
+        .stdout("==> standard input <==\nb\nc\n");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_truncated() -> Result<()> {
+    use std::{thread, time::Duration};
+
+    let dir = tempfile::tempdir()?;
+    let log = dir.path().join("trunc.log");
+    fs::write(&log, "a\nb\n")?;
+
+    let mut writer = std::process::Command::new("sleep").arg("1").spawn()?;
+    let pid = writer.id().to_string();
+    let reaper = thread::spawn(move || writer.wait());
+    let truncater = {
+        let log = log.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(400));
+            fs::write(&log, "x\n")
+        })
+    };
+
+    let name = log.display().to_string();
+    let output = Command::cargo_bin(PRG)?
+        .args(["-f", "-n", "1", "-s", "0.1", "--pid", &pid, &name])
+        .timeout(Duration::from_secs(10))
+        .output()?;
+    reaper.join().unwrap()?;
+    truncater.join().unwrap()?;
+
+    // 提示以程序名开头, 不影响退出码
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "b\nx\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("tailr: {name}: file truncated\n")
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn follow_pipe_ignored() -> Result<()> {
+    // 管道无法跟随, 输出末尾后直接以 0 退出
+    Command::cargo_bin(PRG)?
+        .args(["-f", "-n", "1"])
+        .write_stdin("a\nb\n")
+        .timeout(std::time::Duration::from_secs(5))
+        .assert()
+        .success()
+        .stdout("b\n")
+        .stderr("");
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn size_suffixes() -> Result<()> {
+    let input: String = (1..=3000).map(|n| format!("{n}\n")).collect();
+    let last_k_lines: String = (2001..=3000).map(|n| format!("{n}\n")).collect();
+    Command::cargo_bin(PRG)?
+        .args(["-n", "1kB"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(last_k_lines);
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "1K"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input[input.len() - 1024..].to_string());
+
+    Command::cargo_bin(PRG)?
+        .args(["-c", "+2b"])
+        .write_stdin(input.clone())
+        .assert()
+        .success()
+        .stdout(input[1023..].to_string());
+
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn zero_terminated() -> Result<()> {
+    Command::cargo_bin(PRG)?
+        .args(["-z", "-n", "2"])
+        .write_stdin("a\0b\nb\0c\0")
+        .assert()
+        .success()
+        .stdout("b\nb\0c\0");
+
+    Ok(())
+}
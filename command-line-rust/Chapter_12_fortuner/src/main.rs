@@ -1,16 +1,25 @@
+#[cfg(unix)]
+mod serve;
+mod strfile;
+mod weights;
+
 use anyhow::{anyhow, bail, Result}; // 宏anyhow!()转化错误 bail!()包装错误 //Result 类型
 use clap::Parser;
-use rand::prelude::IndexedRandom; // 切片随机选择功能
-use rand::{rngs::StdRng, RngCore, SeedableRng}; // 随机数生成器
+use rand::distr::{weighted::WeightedIndex, Distribution};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng}; // 随机数生成器
 
 use regex::RegexBuilder;
 use std::{
     ffi::OsStr,
     fs::{self, File},
-    io::{BufRead, BufReader},
-    path::PathBuf,
+    io::{BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
 };
+use strfile::Index;
 use walkdir::WalkDir;
+use weights::{Group, Spec};
 
 // cargo add  clap --features derive
 // cargo add anyhow walkdir regex rand
@@ -19,13 +28,24 @@ use walkdir::WalkDir;
 
 
 #[derive(Debug, Parser)]
-#[command(author, version, about)]
+#[command(author, version, about,
+          after_help = "Run `fortuner index --help` for building .dat index files\n\
+                        Run `fortuner serve --help` for serving fortunes over a socket")]
 /// Rust version of `fortune`
 struct Args {
-    /// Input files or directories
+    /// Input files or directories, each optionally preceded by a
+    /// percentage, e.g. "30% jokes 70% quotes"
     #[arg(required(true), value_name = "FILE")]
     sources: Vec<String>, // 输入文件或目录
 
+    /// Give all files equal probability, regardless of size
+    #[arg(short, long)]
+    equal: bool,
+
+    /// Print the files that would be searched with their probabilities
+    #[arg(short('f'), long("files"))]
+    list: bool,
+
     /// Pattern
     #[arg(short('m'), long)]
     pattern: Option<String>,
@@ -34,23 +54,111 @@ struct Args {
     #[arg(short, long)]
     insensitive: bool, // 大小写不敏感匹配
 
+    #[command(flatten)]
+    filter: FilterArgs,
+
+    /// Wait after printing, long enough to read the fortune
+    #[arg(short, long)]
+    wait: bool,
+
     /// Random seed
-    #[arg(short, long, value_parser(clap::value_parser!(u64)))]
+    #[arg(long, value_parser(clap::value_parser!(u64)))]
     seed: Option<u64>,  // 随机种子
 }
 
-#[derive(Debug)]
+// -s/-l/-n/-o/-a, serve 也使用
+#[derive(Debug, clap::Args)]
+struct FilterArgs {
+    /// Use only short fortunes
+    #[arg(short, long, conflicts_with = "long")]
+    short: bool,
+
+    /// Use only long fortunes
+    #[arg(short, long)]
+    long: bool,
+
+    /// Longest fortune length in bytes considered short
+    #[arg(short('n'), value_name = "LENGTH", default_value_t = 160)]
+    length: usize,
+
+    /// Choose only from offensive fortunes (files named *-o)
+    #[arg(short, long, conflicts_with = "all")]
+    offensive: bool,
+
+    /// Choose from all fortunes, offensive or not
+    #[arg(short, long)]
+    all: bool,
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "fortuner index", bin_name = "fortuner index", version)]
+/// Write a strfile-compatible FILE.dat index next to each fortune file
+struct IndexArgs {
+    /// Input files or directories
+    #[arg(required(true), value_name = "FILE")]
+    sources: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
 struct Fortune {
     source: String,  // 来源文件名
-    text: String,    // 文本内容
+    text: String,    // 文本内容, 冒犯性的条目已经过 rot13 解码
+    len: usize,      // 原文的字节数, 用于 -s/-l
+    offensive: bool, // 是否来自 *-o 文件
+}
+
+// -s/-l 的长度限制
+#[derive(Debug, Clone, Copy)]
+enum Length {
+    Any,
+    Short(usize), // 不超过 LENGTH 字节
+    Long(usize),  // 超过 LENGTH 字节
+}
+
+// 目录中的 *-o 文件是否参与选择, 命令行上直接给出的文件总是参与
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Offensive {
+    Exclude, // 默认
+    Only,    // -o
+    Include, // -a
+}
+
+// 选择条目时的过滤条件
+#[derive(Debug, Clone, Copy)]
+struct Filter {
+    length: Length,
+    offensive: Offensive,
+}
+
+// 一个来源文件
+#[derive(Debug)]
+struct Source {
+    path: PathBuf,
+    percent: f64, // 被选中的概率(百分比)
+    fortunes: Fortunes,
+}
+
+// 有最新的 .dat 索引时只读索引, 否则读入全部内容
+#[derive(Debug)]
+enum Fortunes {
+    Indexed {
+        index: Index,
+        rotated: bool, // 内容经过 rot13 编码
+    },
+    Loaded(Vec<Fortune>),
 }
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
-    }
+    // `fortuner index ...` 生成索引, `fortuner serve ...` 启动服务,
+    // 其余情况按 fortune 解析参数
+    let result = match std::env::args().nth(1).as_deref() {
+        Some("index") => run_index(IndexArgs::parse_from(std::env::args().skip(1))),
+        #[cfg(unix)]
+        Some("serve") => serve::run(serve::Args::parse_from(std::env::args().skip(1))),
+        _ => run(Args::parse()),
+    };
+    clr_core::exit(result);
 }
 
 // --------------------------------------------------
@@ -64,41 +172,54 @@ fn run(args: Args) -> Result<()> {
         })
         .transpose()?; // 将 Option<Result> 转换为 Result<Option>
     
-    // 查找所有输入路径对应的文件
-    let files = find_files(&args.sources)?;
-    // 从文件中读取所有内容
-    let fortunes = read_fortunes(&files)?;
+    let specs = weights::parse(&args.sources)?;
+    let filter = Filter::from(&args.filter);
 
+    let mut out = clr_core::stdout();
     match pattern {
         Some(pattern) => {
+            // 查找所有输入路径对应的文件, 搜索时不考虑百分比
+            let paths: Vec<_> = specs.into_iter().map(|s| s.path).collect();
+            let files = find_files(&paths, filter.offensive)?;
+            // 从文件中读取所有内容
+            let fortunes = read_fortunes(&files, filter.length)?;
             let mut prev_source = None;
             for fortune in fortunes
                 .iter()
                 .filter(|fortune| pattern.is_match(&fortune.text))
             {
-                if prev_source.as_ref().map_or(true, |s| s != &fortune.source)
+                if prev_source.as_ref().is_none_or(|s| s != &fortune.source)
                 {
+                    out.flush()?; // 来源写到标准错误, 先输出之前的条目
                     eprintln!("({})\n%", fortune.source);
                     prev_source = Some(fortune.source.clone());
                 }
-                println!("{}\n%", fortune.text);
+                writeln!(out, "{}\n%", fortune.text)?;
             }
         }
         _ => { // 如果没有模式参数，随机选择一个
-            println!(
-                "{}",
-                pick_fortune(&fortunes, args.seed)
-                    .or_else(|| Some("No fortunes found".to_string()))
-                    .unwrap()
-            );
+            let groups = load_groups(&specs, args.equal, filter)?;
+            if args.list {
+                print_list(&specs, &groups, &mut out)?;
+                return Ok(out.flush()?);
+            }
+
+            let (sources, weighted) = flatten_groups(&specs, groups, args.equal);
+            let text = pick_fortune(&sources, args.seed, weighted)?
+                .unwrap_or_else(|| "No fortunes found".to_string());
+            writeln!(out, "{text}")?;
+            out.flush()?; // 等待之前先显示出来
+            if args.wait {
+                thread::sleep(wait_time(&text));
+            }
         }
     }
-
+    out.flush()?;
     Ok(())
 }
 
 // --------------------------------------------------
-fn find_files(paths: &[String]) -> Result<Vec<PathBuf>> {
+fn find_files(paths: &[String], offensive: Offensive) -> Result<Vec<PathBuf>> {
     let dat = OsStr::new("dat"); // 定义dat扩展名
     let mut files = vec![];
 
@@ -114,6 +235,15 @@ fn find_files(paths: &[String]) -> Result<Vec<PathBuf>> {
                         e.file_type().is_file()
                             && e.path().extension() != Some(dat)
                     })
+                    // depth 为 0 的是命令行上直接给出的文件
+                    .filter(|e| {
+                        e.depth() == 0
+                            || match offensive {
+                                Offensive::Exclude => !is_offensive(e.path()),
+                                Offensive::Only => is_offensive(e.path()),
+                                Offensive::Include => true,
+                            }
+                    })
                     .map(|e| e.path().into()), // into() 转换/包装为结果类型 Result<Vec<PathBuf>>
             ),
         }
@@ -125,46 +255,317 @@ fn find_files(paths: &[String]) -> Result<Vec<PathBuf>> {
 }
 
 // --------------------------------------------------
-fn read_fortunes(paths: &[PathBuf]) -> Result<Vec<Fortune>> {
+fn run_index(args: IndexArgs) -> Result<()> {
+    let mut stdout = clr_core::stdout();
+    for path in find_files(&args.sources, Offensive::Include)? {
+        let file = open(&path)?;
+        let (records, end) = strfile::records(BufReader::new(file))?;
+        let mut index = Index::build(&records, end)
+            .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+        if is_offensive(&path) {
+            index.flags |= strfile::STR_ROTATED;
+        }
+
+        let dat = dat_path(&path);
+        let out = File::create(&dat)
+            .map_err(|e| anyhow!("{}: {e}", dat.display()))?;
+        index.write(BufWriter::new(out))?;
+        writeln!(
+            stdout,
+            "\"{}\" created: {} strings, longest {} bytes, shortest {} bytes",
+            dat.display(),
+            index.numstr(),
+            index.longlen,
+            index.shortlen
+        )?;
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+// --------------------------------------------------
+fn read_fortunes(paths: &[PathBuf], length: Length) -> Result<Vec<Fortune>> {
     let mut fortunes = vec![]; // 最终返回值
-    let mut buffer = vec![]; // 临时缓冲区，用于累积多行文本
 
     for path in paths {
-        // 获取basename
-        let basename =
-            path.file_name().unwrap().to_string_lossy().into_owned(); // into_owned(): 通过 clone()等方式获得所有权
-        // 获取文件句柄
-        let file = File::open(path)
-            .map_err(|e| anyhow!("{}: {e}", path.to_string_lossy()))?;
-        
-        // 逐行读取文件
-        for line in BufReader::new(file).lines().map_while(Result::ok) {
-            if line == "%" {  // 遇到 % 分隔符,表示一个结束符
-                if !buffer.is_empty() { // buffer 非空
-                    fortunes.push(Fortune {
-                        source: basename.clone(),
-                        text: buffer.join("\n"),  // 多行buffer Vec内容 使用"\n" 连接字符串
-                    });
-                    buffer.clear();
-                }
-            } else {
-                buffer.push(line.to_string()); // buffer 追加
+        fortunes.extend(read_file(path, length)?);
+    }
+
+    Ok(fortunes)
+}
+
+// --------------------------------------------------
+fn read_file(path: &Path, length: Length) -> Result<Vec<Fortune>> {
+    // 获取basename
+    let basename = path.file_name().unwrap().to_string_lossy().into_owned(); // into_owned(): 通过 clone()等方式获得所有权
+    let offensive = is_offensive(path);
+    let (records, _) = strfile::records(BufReader::new(open(path)?))?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| Fortune {
+            source: basename.clone(),
+            text: record.text,
+            len: record.len as usize,
+            offensive,
+        })
+        .filter(|fortune| length.accepts(fortune.len))
+        .map(Fortune::decoded)
+        .collect())
+}
+
+// --------------------------------------------------
+// 每个命令行来源展开成文件, 再按百分比分配概率
+fn load_groups(
+    specs: &[Spec],
+    equal: bool,
+    filter: Filter,
+) -> Result<Vec<Vec<Source>>> {
+    let groups = specs
+        .iter()
+        .map(|spec| {
+            find_files(std::slice::from_ref(&spec.path), filter.offensive)?
+                .into_iter()
+                .map(|path| load_source(path, filter.length))
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let sizes: Vec<Group> = specs
+        .iter()
+        .zip(&groups)
+        .map(|(spec, sources)| Group {
+            spec,
+            sizes: sources.iter().map(Source::len).collect(),
+        })
+        .collect();
+    let percents = weights::distribute(&sizes, equal)?;
+
+    Ok(groups
+        .into_iter()
+        .zip(percents)
+        .map(|(sources, percents)| {
+            sources
+                .into_iter()
+                .zip(percents)
+                .map(|(source, percent)| Source { percent, ..source })
+                .collect()
+        })
+        .collect())
+}
+
+// --------------------------------------------------
+// 只有 -e 或给出百分比时才先按概率选文件, 否则在全部条目中均匀选取;
+// 返回所有文件以及是否按概率选取
+fn flatten_groups(
+    specs: &[Spec],
+    groups: Vec<Vec<Source>>,
+    equal: bool,
+) -> (Vec<Source>, bool) {
+    let weighted = equal || specs.iter().any(|s| s.percent.is_some());
+    let mut sources: Vec<Source> = groups.into_iter().flatten().collect();
+    if !weighted {
+        sources.sort_by(|a, b| a.path.cmp(&b.path));
+        sources.dedup_by(|a, b| a.path == b.path);
+    }
+    (sources, weighted)
+}
+
+// --------------------------------------------------
+// 索引比源文件旧时视为过期, 退回到读取整个文件
+fn load_source(path: PathBuf, length: Length) -> Result<Source> {
+    let dat = dat_path(&path);
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified());
+    let index = match (modified(&path), modified(&dat)) {
+        (Ok(text), Ok(index)) if index >= text => Some(
+            Index::read(BufReader::new(open(&dat)?))
+                .map_err(|e| anyhow!("{}: {e}", dat.display()))?,
+        ),
+        _ => None,
+    };
+    // 索引中没有每条记录的长度(偏移之差还包括分隔符行、空记录和 \r),
+    // -s/-l 时读取整个文件, 在内存中计算
+    let fortunes = match index {
+        Some(index) if matches!(length, Length::Any) => {
+            let rotated = is_offensive(&path)
+                || index.flags & strfile::STR_ROTATED != 0;
+            Fortunes::Indexed { index, rotated }
+        }
+        _ => Fortunes::Loaded(read_file(&path, length)?),
+    };
+    Ok(Source { path, percent: 0.0, fortunes })
+}
+
+impl Source {
+    // --------------------------------------------------
+    fn len(&self) -> usize {
+        match &self.fortunes {
+            Fortunes::Indexed { index, .. } => index.numstr(),
+            Fortunes::Loaded(fortunes) => fortunes.len(),
+        }
+    }
+
+    // --------------------------------------------------
+    // 读入全部条目, 不再使用索引
+    #[cfg(unix)]
+    fn loaded(self, length: Length) -> Result<Self> {
+        match self.fortunes {
+            Fortunes::Loaded(_) => Ok(self),
+            Fortunes::Indexed { .. } => {
+                let fortunes = Fortunes::Loaded(read_file(&self.path, length)?);
+                Ok(Source { fortunes, ..self })
             }
         }
     }
 
-    Ok(fortunes)
+    // --------------------------------------------------
+    // 有索引的文件直接 seek 到所选记录
+    fn get(&self, idx: usize) -> Result<String> {
+        match &self.fortunes {
+            Fortunes::Loaded(fortunes) => Ok(fortunes[idx].text.clone()),
+            Fortunes::Indexed { index, rotated } => {
+                let mut file = open(&self.path)?;
+                let offset = index.offsets[idx];
+                file.seek(SeekFrom::Start(offset as u64))?;
+                let text = strfile::read_record(BufReader::new(file), index.delim)?;
+                Ok(if *rotated { rot13(&text) } else { text })
+            }
+        }
+    }
 }
 
 // --------------------------------------------------
-fn pick_fortune(fortunes: &[Fortune], seed: Option<u64>) -> Option<String> {
+// 与 BSD fortune -f 相同: 每个来源一行, 目录下的文件缩进列出
+fn print_list(
+    specs: &[Spec],
+    groups: &[Vec<Source>],
+    out: &mut impl Write,
+) -> Result<()> {
+    for (spec, sources) in specs.iter().zip(groups) {
+        let total: f64 = sources.iter().map(|s| s.percent).sum();
+        writeln!(out, "{total:6.2}% {}", spec.path)?;
+        if Path::new(&spec.path).is_dir() {
+            for source in sources {
+                let name = source.path.strip_prefix(&spec.path).unwrap_or(&source.path);
+                writeln!(out, "    {:6.2}% {}", source.percent, name.display())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+fn pick_fortune(
+    sources: &[Source],
+    seed: Option<u64>,
+    weighted: bool,
+) -> Result<Option<String>> {
     let mut rng: Box<dyn RngCore> = match seed {
         Some(val) => Box::new(StdRng::seed_from_u64(val)), // val 确定随机种子
-        _ => Box::new(rand::thread_rng()),  // 系统随机种子
+        _ => Box::new(rand::rng()),  // 系统随机种子
     };
-    // use rand::prelude::IndexedRandom;
-    // 切片列表中随机选择一个 // 转为字符串 返回
-    fortunes.choose(&mut rng).map(|f| f.text.to_string())
+    let total: usize = sources.iter().map(Source::len).sum();
+    if total == 0 {
+        return Ok(None);
+    }
+
+    if weighted {
+        // 先按百分比选文件, 再在文件内均匀选一条
+        let dist = WeightedIndex::new(sources.iter().map(|s| s.percent))?;
+        let source = &sources[dist.sample(&mut rng)];
+        let idx = rng.random_range(..source.len());
+        return source.get(idx).map(Some);
+    }
+
+    // 与切片的 choose() 取法相同, 有无索引时同一个种子选出同一条
+    let mut pick = rng.random_range(..total);
+    for source in sources {
+        if pick < source.len() {
+            return source.get(pick).map(Some);
+        }
+        pick -= source.len();
+    }
+    unreachable!()
+}
+
+impl From<&FilterArgs> for Filter {
+    // --------------------------------------------------
+    fn from(args: &FilterArgs) -> Self {
+        Filter {
+            length: match (args.short, args.long) {
+                (true, _) => Length::Short(args.length),
+                (_, true) => Length::Long(args.length),
+                _ => Length::Any,
+            },
+            offensive: match (args.offensive, args.all) {
+                (true, _) => Offensive::Only,
+                (_, true) => Offensive::Include,
+                _ => Offensive::Exclude,
+            },
+        }
+    }
+}
+
+impl Fortune {
+    // --------------------------------------------------
+    // 冒犯性的条目以 rot13 编码保存, 读入后解码
+    fn decoded(self) -> Self {
+        match self.offensive {
+            true => Fortune { text: rot13(&self.text), ..self },
+            false => self,
+        }
+    }
+}
+
+impl Length {
+    // --------------------------------------------------
+    fn accepts(self, len: usize) -> bool {
+        match self {
+            Length::Any => true,
+            Length::Short(max) => len <= max,
+            Length::Long(max) => len > max,
+        }
+    }
+}
+
+// --------------------------------------------------
+// 与 BSD fortune 相同: 每秒读 20 个字符, 至少等待 6 秒
+fn wait_time(text: &str) -> Duration {
+    const CHARS_PER_SEC: u64 = 20;
+    const MIN_WAIT: u64 = 6;
+    let secs = text.chars().count() as u64 / CHARS_PER_SEC;
+    Duration::from_secs(secs.max(MIN_WAIT))
+}
+
+// --------------------------------------------------
+// 约定以 -o 结尾的文件收录冒犯性的内容, 并以 rot13 编码保存
+fn is_offensive(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with("-o"))
+}
+
+// --------------------------------------------------
+fn rot13(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'a'..='m' | 'A'..='M' => (c as u8 + 13) as char,
+            'n'..='z' | 'N'..='Z' => (c as u8 - 13) as char,
+            _ => c,
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+fn open(path: &Path) -> Result<File> {
+    File::open(path).map_err(|e| anyhow!("{}: {e}", path.to_string_lossy()))
+}
+
+// --------------------------------------------------
+// strfile 的约定: 索引文件名为源文件名加 .dat
+fn dat_path(path: &Path) -> PathBuf {
+    let mut dat = path.as_os_str().to_owned();
+    dat.push(".dat");
+    dat.into()
 }
 
 // --------------------------------------------------
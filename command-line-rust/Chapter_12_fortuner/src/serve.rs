@@ -0,0 +1,218 @@
+// fortuner serve: 常驻进程, 在内存中保留解析好的条目, 通过 Unix 套接字应答
+//
+// 协议以行为单位, 每个连接可以发送多个请求:
+//   请求: [seed=N] [pattern=REGEX]   pattern 占用该行余下的全部内容
+//   应答: OK, 随后是条目正文和只含 % 的一行; 出错时为 ERR 加错误信息
+// 来源的百分比和 -e/-s/-l/-n/-o/-a 与命令行模式相同
+use crate::{
+    flatten_groups, load_groups, pick_fortune, weights, Filter, FilterArgs,
+    Fortunes, Source,
+};
+use anyhow::{anyhow, bail, Result};
+use clap::Parser;
+use regex::Regex;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    mem,
+    os::unix::{
+        fs::FileTypeExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    process, ptr, thread,
+};
+
+// 同时处理的连接数, 更多的连接在 accept 队列中等待
+const MAX_CLIENTS: usize = 16;
+
+#[derive(Debug, Parser)]
+#[command(name = "fortuner serve", bin_name = "fortuner serve", version)]
+/// Serve random fortunes over a Unix domain socket
+pub struct Args {
+    /// Input files or directories, each optionally preceded by a
+    /// percentage, e.g. "30% jokes 70% quotes"
+    #[arg(required(true), value_name = "FILE")]
+    sources: Vec<String>,
+
+    /// Path of the socket to listen on
+    #[arg(long, value_name = "PATH")]
+    socket: PathBuf,
+
+    /// Give all files equal probability, regardless of size
+    #[arg(short, long)]
+    equal: bool,
+
+    #[command(flatten)]
+    filter: FilterArgs,
+}
+
+// 正常返回或出错时删除套接字文件
+struct Socket(PathBuf);
+
+// 一个请求
+#[derive(Debug, Default)]
+struct Request {
+    seed: Option<u64>,
+    pattern: Option<Regex>,
+}
+
+// --------------------------------------------------
+pub fn run(args: Args) -> Result<()> {
+    let specs = weights::parse(&args.sources)?;
+    let filter = Filter::from(&args.filter);
+    let groups = load_groups(&specs, args.equal, filter)?;
+    let (sources, weighted) = flatten_groups(&specs, groups, args.equal);
+    // 按模式过滤时要用到每条的正文, 全部读入内存
+    let sources = sources
+        .into_iter()
+        .map(|source| source.loaded(filter.length))
+        .collect::<Result<Vec<_>>>()?;
+
+    // 上次运行留下的套接字文件会导致 bind 失败
+    if fs::symlink_metadata(&args.socket).is_ok_and(|m| m.file_type().is_socket()) {
+        fs::remove_file(&args.socket)?;
+    }
+    let listener = UnixListener::bind(&args.socket)
+        .map_err(|e| anyhow!("{}: {e}", args.socket.display()))?;
+    let socket = Socket(args.socket);
+    remove_on_signal(&socket.0)?;
+
+    thread::scope(|s| {
+        for _ in 0..MAX_CLIENTS {
+            let (listener, sources) = (&listener, &sources);
+            s.spawn(move || {
+                for stream in listener.incoming() {
+                    let result = stream
+                        .map_err(Into::into)
+                        .and_then(|stream| handle(stream, sources, weighted));
+                    if let Err(e) = result {
+                        clr_core::warn(e);
+                    }
+                }
+            });
+        }
+    });
+    drop(socket);
+    Ok(())
+}
+
+// --------------------------------------------------
+// SIGINT/SIGTERM/SIGHUP 时删除套接字文件再退出;
+// 必须在启动其他线程之前调用, 新线程继承屏蔽字, 信号只由 sigwait 接收
+fn remove_on_signal(socket: &Path) -> Result<()> {
+    let socket = socket.to_path_buf();
+    // SAFETY: set 由 sigemptyset 初始化后才使用
+    let set = unsafe {
+        let mut set: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut set);
+        for sig in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+            libc::sigaddset(&mut set, sig);
+        }
+        if libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) != 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        set
+    };
+    thread::spawn(move || {
+        let mut sig = 0;
+        // SAFETY: set 已初始化, sig 是有效的输出位置
+        if unsafe { libc::sigwait(&set, &mut sig) } == 0 {
+            let _ = fs::remove_file(&socket);
+            process::exit(128 + sig);
+        }
+    });
+    Ok(())
+}
+
+// --------------------------------------------------
+fn handle(
+    stream: UnixStream,
+    sources: &[Source],
+    weighted: bool,
+) -> Result<()> {
+    let mut out = BufWriter::new(stream.try_clone()?);
+
+    for line in BufReader::new(stream).lines() {
+        let response = parse_request(&line?)
+            .and_then(|request| pick(sources, weighted, &request));
+        match response {
+            Ok(text) => {
+                let text = text.as_deref().unwrap_or("No fortunes found");
+                write!(out, "OK\n{text}\n%\n")?;
+            }
+            Err(e) => writeln!(out, "ERR {e}")?,
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+fn parse_request(line: &str) -> Result<Request> {
+    let mut request = Request::default();
+    let mut rest = line.trim_start();
+
+    while !rest.is_empty() {
+        if let Some(pattern) = rest.strip_prefix("pattern=") {
+            request.pattern = Some(
+                Regex::new(pattern)
+                    .map_err(|_| anyhow!(r#"Invalid pattern "{pattern}""#))?,
+            );
+            break;
+        }
+        let (word, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+        match word.strip_prefix("seed=") {
+            Some(seed) => {
+                request.seed = Some(
+                    seed.parse()
+                        .map_err(|_| anyhow!(r#"Invalid seed "{seed}""#))?,
+                );
+            }
+            None => bail!(r#"Unknown request "{word}""#),
+        }
+        rest = tail.trim_start();
+    }
+    Ok(request)
+}
+
+// --------------------------------------------------
+// 与命令行模式相同的取法, 同一个种子选出同一条;
+// 有 pattern 时只在匹配的条目中选取, 没有匹配条目的文件不参与
+fn pick(
+    sources: &[Source],
+    weighted: bool,
+    request: &Request,
+) -> Result<Option<String>> {
+    let Some(pattern) = &request.pattern else {
+        return pick_fortune(sources, request.seed, weighted);
+    };
+    let matching: Vec<Source> = sources
+        .iter()
+        .filter_map(|source| {
+            let Fortunes::Loaded(fortunes) = &source.fortunes else {
+                unreachable!("serve reads every source into memory");
+            };
+            let fortunes: Vec<_> = fortunes
+                .iter()
+                .filter(|f| pattern.is_match(&f.text))
+                .cloned()
+                .collect();
+            // 按概率选取时, 概率为 0 的文件不会被选中
+            let chosen = !weighted || source.percent > 0.0;
+            (chosen && !fortunes.is_empty()).then(|| Source {
+                path: source.path.clone(),
+                percent: source.percent,
+                fortunes: Fortunes::Loaded(fortunes),
+            })
+        })
+        .collect();
+    pick_fortune(&matching, request.seed, weighted)
+}
+
+impl Drop for Socket {
+    // --------------------------------------------------
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
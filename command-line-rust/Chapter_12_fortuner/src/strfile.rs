@@ -0,0 +1,146 @@
+// strfile 兼容的 .dat 索引
+// 格式: 24 字节的头部(均为大端 u32), 随后是 numstr + 1 个记录起始偏移,
+// 最后一个偏移是文件末尾. 有了索引就可以直接 seek 到某条记录, 不必解析整个文件
+use anyhow::{bail, Result};
+use std::io::{BufRead, Read, Write};
+
+pub const VERSION: u32 = 2;
+pub const DELIMITER: u8 = b'%';
+pub const STR_ROTATED: u32 = 0x4; // 内容经过 rot13 编码
+
+#[derive(Debug, PartialEq)]
+pub struct Index {
+    pub longlen: u32,      // 最长记录的字节数
+    pub shortlen: u32,     // 最短记录的字节数
+    pub flags: u32,        // STR_ROTATED 等标志
+    pub delim: u8,         // 记录分隔符
+    pub offsets: Vec<u32>, // 每条记录的起始偏移, 末尾多一个文件长度
+}
+
+// 文件中的一条记录
+#[derive(Debug)]
+pub struct Record {
+    pub offset: u64, // 第一行的起始字节
+    pub len: u64,    // 不含分隔符行的字节数
+    pub text: String,
+}
+
+impl Index {
+    // --------------------------------------------------
+    pub fn build(records: &[Record], end: u64) -> Result<Self> {
+        let offset = |pos: u64| {
+            u32::try_from(pos).or_else(|_| bail!("file too large to index"))
+        };
+        let lens = records.iter().map(|r| r.len as u32);
+        Ok(Index {
+            longlen: lens.clone().max().unwrap_or(0),
+            shortlen: lens.min().unwrap_or(0),
+            flags: 0,
+            delim: DELIMITER,
+            offsets: records
+                .iter()
+                .map(|r| offset(r.offset))
+                .chain([offset(end)])
+                .collect::<Result<_>>()?,
+        })
+    }
+
+    // --------------------------------------------------
+    pub fn numstr(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    // --------------------------------------------------
+    pub fn write(&self, mut out: impl Write) -> Result<()> {
+        let header = [
+            VERSION,
+            self.numstr() as u32,
+            self.longlen,
+            self.shortlen,
+            self.flags,
+        ];
+        for val in header {
+            out.write_all(&val.to_be_bytes())?;
+        }
+        out.write_all(&[self.delim, 0, 0, 0])?; // stuff[4], 首字节为分隔符
+        for val in &self.offsets {
+            out.write_all(&val.to_be_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    // --------------------------------------------------
+    pub fn read(mut input: impl Read) -> Result<Self> {
+        let mut buf = vec![];
+        input.read_to_end(&mut buf)?;
+        let words: Vec<u32> = buf
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+
+        match words.as_slice() {
+            [1 | 2, numstr, longlen, shortlen, flags, _, offsets @ ..]
+                if buf.len() % 4 == 0 && offsets.len() == *numstr as usize + 1 =>
+            {
+                Ok(Index {
+                    longlen: *longlen,
+                    shortlen: *shortlen,
+                    flags: *flags,
+                    delim: buf[20],
+                    offsets: offsets.to_vec(),
+                })
+            }
+            _ => bail!("not a valid strfile index"),
+        }
+    }
+}
+
+// --------------------------------------------------
+// 逐行读取, 以只含分隔符的行切分记录, 空记录跳过, 最后一条必须以分隔符结尾
+pub fn records(mut input: impl BufRead) -> Result<(Vec<Record>, u64)> {
+    let mut records = vec![];
+    let (mut pos, mut start) = (0, 0);
+    let mut buffer = vec![];
+    let mut line = vec![];
+
+    loop {
+        line.clear();
+        let bytes = input.read_until(b'\n', &mut line)? as u64;
+        if bytes == 0 {
+            return Ok((records, pos));
+        }
+        pos += bytes;
+
+        let content = line.strip_suffix(b"\n").unwrap_or(&line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+        if content == [DELIMITER] {
+            if !buffer.is_empty() {
+                records.push(Record {
+                    offset: start,
+                    len: pos - bytes - start,
+                    text: buffer.join("\n"),
+                });
+                buffer.clear();
+            }
+            start = pos;
+        } else {
+            buffer.push(String::from_utf8_lossy(content).into_owned());
+        }
+    }
+}
+
+// --------------------------------------------------
+// 从记录起始位置读到分隔符行为止
+pub fn read_record(input: impl BufRead, delim: u8) -> Result<String> {
+    let mut lines = vec![];
+    for line in input.split(b'\n') {
+        let line = line?;
+        let content = line.strip_suffix(b"\r").unwrap_or(&line);
+        if content == [delim] {
+            break;
+        }
+        lines.push(String::from_utf8_lossy(content).into_owned());
+    }
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,114 @@
+// BSD fortune 的来源概率: `30% jokes 70% quotes`
+// 没有写明百分比的来源平分剩余的概率, 默认按条数比例, -e 时每个文件相同
+use anyhow::{anyhow, bail, Result};
+
+// 命令行上的一个来源及其百分比
+#[derive(Debug, PartialEq)]
+pub struct Spec {
+    pub percent: Option<f64>,
+    pub path: String,
+}
+
+// 一个来源展开后的文件, sizes 是每个文件中的条数
+#[derive(Debug)]
+pub struct Group<'a> {
+    pub spec: &'a Spec,
+    pub sizes: Vec<usize>,
+}
+
+// --------------------------------------------------
+// 百分比可以单独作为一个参数, 也可以直接写在文件名前面, 如 30%jokes
+pub fn parse(args: &[String]) -> Result<Vec<Spec>> {
+    let mut specs = vec![];
+    let mut pending = None;
+
+    for arg in args {
+        let percent = arg
+            .split_once('%')
+            .filter(|(num, _)| {
+                !num.is_empty() && num.chars().all(|c| c.is_ascii_digit() || c == '.')
+            })
+            .and_then(|(num, rest)| num.parse::<f64>().ok().map(|n| (n, rest)));
+
+        match percent {
+            Some((num, _)) if num > 100.0 => {
+                bail!("percentages must be <= 100 (got {arg})")
+            }
+            Some((num, _)) if pending.is_some() => {
+                bail!("{num}% must be followed by a file, not another percentage")
+            }
+            Some((num, "")) => pending = Some(num),
+            Some((num, rest)) => specs.push(Spec {
+                percent: Some(num),
+                path: rest.to_string(),
+            }),
+            None => specs.push(Spec {
+                percent: pending.take(),
+                path: arg.to_string(),
+            }),
+        }
+    }
+
+    if let Some(num) = pending {
+        bail!("{num}% must be followed by a file");
+    }
+    Ok(specs)
+}
+
+// --------------------------------------------------
+// 计算每个文件的百分比, 返回值与 groups 及其 sizes 一一对应
+pub fn distribute(groups: &[Group], equal: bool) -> Result<Vec<Vec<f64>>> {
+    let given: f64 = groups.iter().filter_map(|g| g.spec.percent).sum();
+    if given > 100.0 + 1e-9 {
+        bail!("percentages add up to {given}%, more than 100%");
+    }
+
+    let mut percents: Vec<Vec<f64>> = Vec::with_capacity(groups.len());
+    for group in groups {
+        percents.push(match group.spec.percent {
+            Some(percent) => share(percent, &group.sizes, equal)
+                .ok_or_else(|| {
+                    anyhow!("{}: no fortunes to give {percent}%", group.spec.path)
+                })?,
+            None => vec![0.0; group.sizes.len()],
+        });
+    }
+
+    // 其余来源合在一起平分剩下的概率
+    let residual = (100.0 - given).max(0.0);
+    let unweighted: Vec<usize> = groups
+        .iter()
+        .filter(|g| g.spec.percent.is_none())
+        .flat_map(|g| g.sizes.iter().copied())
+        .collect();
+    match share(residual, &unweighted, equal) {
+        Some(shares) => {
+            let mut shares = shares.into_iter();
+            for (group, percents) in groups.iter().zip(&mut percents) {
+                if group.spec.percent.is_none() {
+                    percents.iter_mut().for_each(|p| *p = shares.next().unwrap());
+                }
+            }
+        }
+        None if residual > 1e-9 => {
+            bail!("no place to put residual probability ({residual}%)")
+        }
+        None => (),
+    }
+
+    Ok(percents)
+}
+
+// --------------------------------------------------
+// 把 percent 分给各文件, 没有内容的文件不分; 全都没有内容时返回 None
+fn share(percent: f64, sizes: &[usize], equal: bool) -> Option<Vec<f64>> {
+    let weight = |size: usize| match (size, equal) {
+        (0, _) => 0.0,
+        (_, true) => 1.0,
+        (size, false) => size as f64,
+    };
+    let total: f64 = sizes.iter().map(|&s| weight(s)).sum();
+    (total > 0.0).then(|| {
+        sizes.iter().map(|&s| percent * weight(s) / total).collect()
+    })
+}
@@ -2,7 +2,7 @@ use anyhow::Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
-use rand::{distributions::Alphanumeric, Rng};
+use rand::{distr::Alphanumeric, Rng};
 use std::fs;
 
 const PRG: &str = "fortuner";
@@ -14,7 +14,7 @@ const QUOTES: &str = "./tests/inputs/quotes";
 
 // --------------------------------------------------
 fn random_string() -> String {
-    rand::thread_rng()
+    rand::rng()
         .sample_iter(&Alphanumeric)
         .take(7)
         .map(char::from)
@@ -91,7 +91,7 @@ fn no_fortunes_found() -> Result<()> {
 #[test]
 fn quotes_seed_1() -> Result<()> {
     run(
-        &[QUOTES, "-s", "1"],
+        &[QUOTES, "--seed", "1"],
         "You can observe a lot just by watching.\n-- Yogi Berra\n",
     )
 }
@@ -100,7 +100,7 @@ fn quotes_seed_1() -> Result<()> {
 #[test]
 fn jokes_seed_1() -> Result<()> {
     run(
-        &[JOKES, "-s", "1"],
+        &[JOKES, "--seed", "1"],
         "Q: What happens when frogs park illegally?\nA: They get toad.\n",
     )
 }
@@ -109,7 +109,7 @@ fn jokes_seed_1() -> Result<()> {
 #[test]
 fn dir_seed_10() -> Result<()> {
     run(
-        &[FORTUNE_DIR, "-s", "10"],
+        &[FORTUNE_DIR, "--seed", "10"],
         "Q: Why did the fungus and the alga marry?\n\
         A: Because they took a lichen to each other!\n",
     )
@@ -202,3 +202,402 @@ fn mark_twain_lower_i() -> Result<()> {
         "tests/expected/twain_lower_i.err",
     )
 }
+
+// --------------------------------------------------
+fn gen_fortunes(name: &str) -> Result<std::path::PathBuf> {
+    let dir = std::env::temp_dir()
+        .join(format!("fortuner-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a"), "one\nline\n%\n%\ntwo\n\n%\nthree\n%\n")?;
+    fs::write(dir.join("b"), "x\n%\ny\n%\n")?;
+    Ok(dir)
+}
+
+// --------------------------------------------------
+fn picks(args: &[&str]) -> Result<Vec<String>> {
+    (1..=8)
+        .map(|seed| {
+            let output = Command::cargo_bin(PRG)?
+                .args(args)
+                .args(["--seed", &seed.to_string()])
+                .output()?;
+            assert!(output.status.success());
+            Ok(String::from_utf8(output.stdout)?)
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+#[test]
+fn index_strfile_format() -> Result<()> {
+    let dir = gen_fortunes("index")?;
+    let dir_str = dir.to_string_lossy().to_string();
+    let unindexed = picks(&[&dir_str])?;
+
+    Command::cargo_bin(PRG)?
+        .args(["index", &dir_str])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("3 strings, longest 9 bytes"));
+
+    // 版本 2, 3 条, 最长 9, 最短 5, 标志 0, 分隔符 %, 偏移 0 13 20 28
+    let words: Vec<u32> = [2, 3, 9, 5, 0, 0x2500_0000, 0, 13, 20, 28].into();
+    let expected: Vec<u8> =
+        words.iter().flat_map(|w| w.to_be_bytes()).collect();
+    assert_eq!(fs::read(dir.join("a.dat"))?, expected);
+
+    // 有索引时同一个种子选出同一条
+    assert_eq!(picks(&[&dir_str])?, unindexed);
+
+    // 源文件比索引新时不使用索引
+    fs::write(dir.join("b"), "z\n%\n")?;
+    let picked = picks(&[&dir_str])?;
+    assert!(picked.contains(&"z\n".to_string()));
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn index_length_filter() -> Result<()> {
+    // CRLF、空记录和没有结尾分隔符的最后一条都让偏移之差不等于记录长度
+    let dir = std::env::temp_dir()
+        .join(format!("fortuner-index-len-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let file = dir.join("c");
+    fs::write(
+        &file,
+        "ab\r\n%\r\n%\r\nlonger text\r\n%\r\nxy\r\n%\r\ntail\r\n",
+    )?;
+    let file_str = file.to_string_lossy().to_string();
+    let args = [file_str.as_str(), "-s", "-n", "5"];
+    let unindexed = picks(&args)?;
+    assert!(unindexed.iter().all(|p| p == "ab\n" || p == "xy\n"));
+
+    Command::cargo_bin(PRG)?
+        .args(["index", &file_str])
+        .assert()
+        .success();
+    assert_eq!(picks(&args)?, unindexed);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_index() -> Result<()> {
+    let dir = gen_fortunes("bad-index")?;
+    fs::write(dir.join("b.dat"), "garbage")?;
+    Command::cargo_bin(PRG)?
+        .args([dir.to_str().unwrap(), "--seed", "1"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("b.dat: not a valid strfile index"));
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn list_percentages() -> Result<()> {
+    let dir = gen_fortunes("list")?;
+    let dir_str = dir.to_string_lossy().to_string();
+    let b = dir.join("b").to_string_lossy().to_string();
+
+    // 默认按条数比例: a 有 3 条, b 有 2 条
+    let expected = format!("100.00% {dir_str}\n     60.00% a\n     40.00% b\n");
+    Command::cargo_bin(PRG)?
+        .args(["-f", &dir_str])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let expected = format!("100.00% {dir_str}\n     50.00% a\n     50.00% b\n");
+    Command::cargo_bin(PRG)?
+        .args(["-e", "-f", &dir_str])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    let expected = format!(" 10.00% {b}\n 90.00% {dir_str}\n     54.00% a\n     36.00% b\n");
+    Command::cargo_bin(PRG)?
+        .args(["-f", "10%", &b, &dir_str])
+        .assert()
+        .success()
+        .stdout(expected);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn weighted_sources() -> Result<()> {
+    let dir = gen_fortunes("weighted")?;
+    let a = dir.join("a").to_string_lossy().to_string();
+    let b = dir.join("b").to_string_lossy().to_string();
+
+    // 100% 给 b 时只会选到 b 中的条目
+    for seed in 1..=10 {
+        let output = Command::cargo_bin(PRG)?
+            .args(["100%", &b, &a, "--seed", &seed.to_string()])
+            .output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        assert!(stdout == "x\n" || stdout == "y\n", "{stdout}");
+    }
+
+    // 同一个种子总是选出同一条
+    let args = ["-e", &a, &b, "--seed", "3"];
+    let first = Command::cargo_bin(PRG)?.args(args).output()?;
+    let second = Command::cargo_bin(PRG)?.args(args).output()?;
+    assert_eq!(first.stdout, second.stdout);
+
+    Command::cargo_bin(PRG)?
+        .args(["60%", &a, "50%", &b])
+        .assert()
+        .failure()
+        .stderr("fortuner: percentages add up to 110%, more than 100%\n");
+    Command::cargo_bin(PRG)?
+        .args(["60%", &a])
+        .assert()
+        .failure()
+        .stderr("fortuner: no place to put residual probability (40%)\n");
+    Command::cargo_bin(PRG)?
+        .args([&a, "30%"])
+        .assert()
+        .failure()
+        .stderr("fortuner: 30% must be followed by a file\n");
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn length_and_offensive() -> Result<()> {
+    let dir = std::env::temp_dir()
+        .join(format!("fortuner-length-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a"), "short one\n%\na rather longer fortune\n%\n")?;
+    fs::write(dir.join("a-o"), "shor\n%\n")?; // rot13: "fube"
+    let dir_str = dir.to_string_lossy().to_string();
+
+    let search = |args: &[&str], expected: &str| -> Result<()> {
+        Command::cargo_bin(PRG)?
+            .args(["-m", "."])
+            .args(args)
+            .arg(&dir_str)
+            .assert()
+            .success()
+            .stdout(expected.to_string());
+        Ok(())
+    };
+    // 默认不包括 *-o 文件
+    search(&[], "short one\n%\na rather longer fortune\n%\n")?;
+    search(&["-o"], "fube\n%\n")?;
+    search(&["-a", "-s", "-n", "10"], "short one\n%\nfube\n%\n")?;
+    search(&["-a", "-l", "-n", "10"], "a rather longer fortune\n%\n")?;
+
+    // 使用索引时结果相同
+    Command::cargo_bin(PRG)?.args(["index", &dir_str]).assert().success();
+    search(&["-o"], "fube\n%\n")?;
+    for seed in ["1", "2", "3"] {
+        Command::cargo_bin(PRG)?
+            .args(["-a", "-l", "-n", "10", &dir_str, "--seed", seed])
+            .assert()
+            .success()
+            .stdout("a rather longer fortune\n");
+    }
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+// 测试结束或断言失败时结束服务进程
+#[cfg(unix)]
+struct Server(std::process::Child);
+
+#[cfg(unix)]
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+// --------------------------------------------------
+// 启动 fortuner serve 并连接到套接字
+#[cfg(unix)]
+fn serve(
+    args: &[&str],
+    socket: &std::path::Path,
+) -> Result<(Server, std::os::unix::net::UnixStream)> {
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, Instant};
+
+    let mut server =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(["serve", "--socket", socket.to_str().unwrap()])
+            .args(args)
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+
+    // 服务端启动失败时不要一直等下去
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match UnixStream::connect(socket) {
+            Ok(stream) => return Ok((Server(server), stream)),
+            Err(e) if Instant::now() >= deadline => {
+                server.kill()?;
+                let output = server.wait_with_output()?;
+                panic!(
+                    "cannot connect to {}: {e}\n{}",
+                    socket.display(),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+}
+
+// --------------------------------------------------
+// 发送一行请求, 读取完整的应答
+#[cfg(unix)]
+fn request(
+    stream: &mut std::os::unix::net::UnixStream,
+    line: &str,
+) -> Result<String> {
+    use std::io::{BufRead, BufReader, Write};
+
+    writeln!(stream, "{line}")?;
+    let mut reader = BufReader::new(&*stream);
+    let mut response = String::new();
+    reader.read_line(&mut response)?;
+    if response == "OK\n" {
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            response.push_str(&line);
+            if line == "%\n" {
+                break;
+            }
+        }
+    }
+    Ok(response)
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn serve_socket() -> Result<()> {
+    let dir = gen_fortunes("serve")?;
+    let dir_str = dir.to_string_lossy().to_string();
+    let socket = dir.join("sock");
+    let (mut server, mut stream) = serve(&[&dir_str], &socket)?;
+
+    // 同一个种子与命令行模式选出同一条
+    let expected = String::from_utf8(
+        Command::cargo_bin(PRG)?
+            .args([&dir_str, "--seed", "5"])
+            .output()?
+            .stdout,
+    )?;
+    assert_eq!(request(&mut stream, "seed=5")?, format!("OK\n{expected}%\n"));
+    assert_eq!(request(&mut stream, "seed=1 pattern=^thr")?, "OK\nthree\n%\n");
+    assert_eq!(
+        request(&mut stream, "pattern=nothing")?,
+        "OK\nNo fortunes found\n%\n"
+    );
+    assert_eq!(request(&mut stream, "seed=x")?, "ERR Invalid seed \"x\"\n");
+    assert_eq!(
+        request(&mut stream, "pattern=*")?,
+        "ERR Invalid pattern \"*\"\n"
+    );
+
+    // SIGTERM 时删除套接字文件
+    std::process::Command::new("kill")
+        .arg(server.0.id().to_string())
+        .status()?;
+    server.0.wait()?;
+    let exists = socket.exists();
+    fs::remove_dir_all(&dir)?;
+    assert!(!exists, "socket not removed");
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(unix)]
+fn serve_weights_and_length() -> Result<()> {
+    let dir = gen_fortunes("serve-filters")?;
+    let a = dir.join("a").to_string_lossy().to_string();
+    let b = dir.join("b").to_string_lossy().to_string();
+
+    // 100% 给 b 时只会选到 b 中的条目
+    let (server, mut stream) =
+        serve(&["100%", &b, &a], &dir.join("weighted"))?;
+    for seed in 1..=10 {
+        let response = request(&mut stream, &format!("seed={seed}"))?;
+        assert!(response == "OK\nx\n%\n" || response == "OK\ny\n%\n");
+    }
+    assert_eq!(
+        request(&mut stream, "pattern=^thr")?,
+        "OK\nNo fortunes found\n%\n"
+    );
+    drop(server);
+
+    // -s -n 3 时只有 b 中的短条目, 与命令行模式选出同一条
+    let (server, mut stream) =
+        serve(&["-s", "-n", "3", &a, &b], &dir.join("short"))?;
+    for seed in ["1", "2", "3"] {
+        let expected = String::from_utf8(
+            Command::cargo_bin(PRG)?
+                .args(["-s", "-n", "3", &a, &b, "--seed", seed])
+                .output()?
+                .stdout,
+        )?;
+        assert_eq!(
+            request(&mut stream, &format!("seed={seed}"))?,
+            format!("OK\n{expected}%\n")
+        );
+    }
+    assert_eq!(
+        request(&mut stream, "pattern=e")?,
+        "OK\nNo fortunes found\n%\n"
+    );
+    drop(server);
+
+    fs::remove_dir_all(&dir)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn wait_after_printing() -> Result<()> {
+    use std::io::{BufRead, BufReader};
+    use std::time::Duration;
+
+    let dir = gen_fortunes("wait")?;
+    let mut child =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin(PRG))
+            .args(["-w", &dir.join("b").to_string_lossy(), "--seed", "1"])
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+
+    // 先输出条目, 然后至少等待 6 秒才退出
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap()).read_line(&mut line)?;
+    std::thread::sleep(Duration::from_millis(500));
+    let running = child.try_wait()?.is_none();
+    child.kill()?;
+    child.wait()?;
+    fs::remove_dir_all(&dir)?;
+
+    assert!(line == "x\n" || line == "y\n", "{line}");
+    assert!(running, "exited without waiting");
+    Ok(())
+}
@@ -0,0 +1,122 @@
+// 历法换算: 用儒略日数(JDN)表示日期, 这样儒略历和格里高利历的日期可以比较,
+// 1752 年 9 月 2 日之后紧接着就是 9 月 14 日
+use chrono::{Datelike, IsoWeek, NaiveDate};
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Reform {
+    /// Julian until 2 September 1752, Gregorian from 14 September 1752
+    #[value(name = "1752")]
+    Y1752,
+    /// Proleptic Gregorian calendar
+    Gregorian,
+    /// Proleptic Julian calendar
+    Julian,
+}
+
+// 英国及其殖民地改历后的第一天
+const REFORM_1752: (i32, u32, u32) = (1752, 9, 14);
+// 被跳过的日期 1752-09-03 至 1752-09-13
+const SKIPPED_1752: std::ops::RangeInclusive<u32> = 3..=13;
+// 0001-01-01(格里高利历)的 JDN 减一, 与 chrono 的 num_days_from_ce 对应
+const CE_OFFSET: i64 = 1_721_425;
+
+// 一个月中的一天
+#[derive(Debug, Clone, Copy)]
+pub struct Day {
+    pub day: u32, // 月中的日期
+    pub jdn: i64, // 儒略日数
+}
+
+impl Reform {
+    // --------------------------------------------------
+    // 该日期是否按格里高利历计算
+    fn is_gregorian(self, year: i32, month: u32, day: u32) -> bool {
+        match self {
+            Reform::Gregorian => true,
+            Reform::Julian => false,
+            Reform::Y1752 => (year, month, day) >= REFORM_1752,
+        }
+    }
+
+    // --------------------------------------------------
+    fn is_leap(self, year: i32) -> bool {
+        if self.is_gregorian(year, 12, 31) {
+            year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+        } else {
+            year % 4 == 0
+        }
+    }
+
+    // --------------------------------------------------
+    pub fn jdn(self, year: i32, month: u32, day: u32) -> i64 {
+        // Fliegel & Van Flandern 的算法, 从三月开始计算年份
+        let a = (14 - month as i64) / 12;
+        let y = year as i64 + 4800 - a;
+        let m = month as i64 + 12 * a - 3;
+        let days = day as i64 + (153 * m + 2) / 5 + 365 * y + y / 4;
+        if self.is_gregorian(year, month, day) {
+            days - y / 100 + y / 400 - 32045
+        } else {
+            days - 32083
+        }
+    }
+
+    // --------------------------------------------------
+    // 一个月中实际存在的所有日期
+    pub fn month_days(self, year: i32, month: u32) -> Vec<Day> {
+        let last = match month {
+            2 if self.is_leap(year) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        (1..=last)
+            .filter(|day| {
+                self != Reform::Y1752
+                    || (year, month) != (1752, 9)
+                    || !SKIPPED_1752.contains(day)
+            })
+            .map(|day| Day { day, jdn: self.jdn(year, month, day) })
+            .collect()
+    }
+
+    // --------------------------------------------------
+    // 一年中的第几天, 与 cal -j 一样改历时跳过的日期也计算在内,
+    // 所以 1752 年 9 月 14 日是第 258 天, 12 月 31 日是第 366 天
+    pub fn day_of_year(self, year: i32, jdn: i64) -> i64 {
+        let (reform_year, month, day) = REFORM_1752;
+        let skipped = match self {
+            Reform::Y1752
+                if year == reform_year && jdn >= self.jdn(year, month, day) =>
+            {
+                SKIPPED_1752.count() as i64
+            }
+            _ => 0,
+        };
+        jdn - self.jdn(year, 1, 1) + 1 + skipped
+    }
+}
+
+// --------------------------------------------------
+// 0 表示星期日
+pub fn weekday_from_sunday(jdn: i64) -> u32 {
+    (jdn + 1).rem_euclid(7) as u32
+}
+
+// --------------------------------------------------
+pub fn gregorian_jdn(date: NaiveDate) -> i64 {
+    date.num_days_from_ce() as i64 + CE_OFFSET
+}
+
+// --------------------------------------------------
+pub fn to_gregorian(jdn: i64) -> Option<NaiveDate> {
+    let days = i32::try_from(jdn - CE_OFFSET).ok()?;
+    NaiveDate::from_num_days_from_ce_opt(days)
+}
+
+// --------------------------------------------------
+// ISO 周数总是按格里高利历计算
+pub fn iso_week(jdn: i64) -> Option<IsoWeek> {
+    to_gregorian(jdn).map(|date| date.iso_week())
+}
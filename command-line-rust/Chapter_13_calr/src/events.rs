@@ -0,0 +1,386 @@
+// --events FILE: 从 iCalendar(.ics) 或 TOML 文件读取节假日和值班等事件
+//
+// .ics 只支持 VEVENT 的 DTSTART、SUMMARY 和 RRULE 的常用部分:
+//   FREQ=DAILY|WEEKLY|MONTHLY|YEARLY, INTERVAL, COUNT, UNTIL, BYDAY, BYMONTH
+// TOML 为事件列表:
+//   [[event]]
+//   date = 2026-10-01
+//   summary = "National Day"
+//   repeat = "yearly"   # 可选: daily|weekly|monthly|yearly
+//   interval = 1        # 可选
+use anyhow::{Result, anyhow, bail};
+use chrono::{Datelike, Days, Months, NaiveDate, Weekday};
+use serde::Deserialize;
+use std::{fs, path::Path};
+
+#[derive(Debug)]
+pub struct Event {
+    pub start: NaiveDate,
+    pub summary: String,
+    pub rule: Option<Rule>,
+}
+
+// 重复规则
+#[derive(Debug, Default)]
+pub struct Rule {
+    pub freq: Freq,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<NaiveDate>,
+    pub by_day: Vec<(Option<i32>, Weekday)>, // 如 2MO 为 (Some(2), Mon), -1FR 为 (Some(-1), Fri)
+    pub by_month: Vec<u32>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Freq {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+// TOML 文件的结构
+#[derive(Debug, Deserialize)]
+struct TomlFile {
+    #[serde(default)]
+    event: Vec<TomlEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TomlEvent {
+    date: TomlDate,
+    summary: String,
+    repeat: Option<Freq>,
+    interval: Option<u32>,
+}
+
+// 日期可以写成 TOML 日期, 也可以写成字符串
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TomlDate {
+    Date(toml::value::Datetime),
+    Text(String),
+}
+
+// --------------------------------------------------
+pub fn load(path: &str) -> Result<Vec<Event>> {
+    let parse = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("ics") => parse_ics,
+        Some("toml") => parse_toml,
+        _ => bail!("{path}: events file must end in .ics or .toml"),
+    };
+    let text = fs::read_to_string(path).map_err(|e| anyhow!("{path}: {e}"))?;
+    parse(&text).map_err(|e| anyhow!("{path}: {e}"))
+}
+
+// --------------------------------------------------
+fn parse_toml(text: &str) -> Result<Vec<Event>> {
+    let file: TomlFile = toml::from_str(text)?;
+    file.event
+        .into_iter()
+        .map(|event| {
+            let date = match event.date {
+                TomlDate::Date(date) => date.to_string(),
+                TomlDate::Text(text) => text,
+            };
+            Ok(Event {
+                start: NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                    .map_err(|_| anyhow!(r#"invalid date "{date}""#))?,
+                summary: event.summary,
+                rule: event.repeat.map(|freq| Rule {
+                    freq,
+                    interval: event.interval.unwrap_or(1).max(1),
+                    ..Rule::default()
+                }),
+            })
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+fn parse_ics(text: &str) -> Result<Vec<Event>> {
+    // 以空格或制表符开头的行是上一行的延续
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        match line.strip_prefix([' ', '\t']) {
+            Some(rest) if !lines.is_empty() => {
+                lines.last_mut().unwrap().push_str(rest)
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let mut events = vec![];
+    let mut current: Option<(Option<NaiveDate>, String, Option<Rule>)> = None;
+    for line in &lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // 去掉 DTSTART;VALUE=DATE 之类的参数
+        let name = name.split(';').next().unwrap_or_default();
+        match (name, &mut current) {
+            ("BEGIN", None) if value == "VEVENT" => {
+                current = Some((None, String::new(), None))
+            }
+            ("END", Some(_)) if value == "VEVENT" => {
+                let (start, summary, rule) = current.take().unwrap();
+                let start =
+                    start.ok_or_else(|| anyhow!("VEVENT without DTSTART"))?;
+                events.push(Event {
+                    start,
+                    summary,
+                    rule,
+                });
+            }
+            ("DTSTART", Some((start, _, _))) => {
+                *start = Some(parse_ics_date(value)?)
+            }
+            ("SUMMARY", Some((_, summary, _))) => *summary = unescape(value),
+            ("RRULE", Some((_, _, rule))) => *rule = Some(parse_rrule(value)?),
+            _ => (),
+        }
+    }
+    Ok(events)
+}
+
+// --------------------------------------------------
+// 20261001 或 20261001T090000Z, 只取日期部分
+fn parse_ics_date(value: &str) -> Result<NaiveDate> {
+    value
+        .get(..8)
+        .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .ok_or_else(|| anyhow!(r#"invalid date "{value}""#))
+}
+
+// --------------------------------------------------
+fn unescape(value: &str) -> String {
+    value
+        .replace("\\n", " ")
+        .replace("\\N", " ")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+// --------------------------------------------------
+fn parse_rrule(value: &str) -> Result<Rule> {
+    let mut rule = Rule {
+        interval: 1,
+        ..Rule::default()
+    };
+    let mut freq = None;
+    for part in value.split(';').filter(|p| !p.is_empty()) {
+        let (key, val) = part
+            .split_once('=')
+            .ok_or_else(|| anyhow!(r#"invalid RRULE part "{part}""#))?;
+        let number = || {
+            val.parse::<u32>()
+                .map_err(|_| anyhow!(r#"invalid RRULE part "{part}""#))
+        };
+        match key {
+            "FREQ" => {
+                freq = Some(match val {
+                    "DAILY" => Freq::Daily,
+                    "WEEKLY" => Freq::Weekly,
+                    "MONTHLY" => Freq::Monthly,
+                    "YEARLY" => Freq::Yearly,
+                    _ => bail!(r#"unsupported RRULE frequency "{val}""#),
+                })
+            }
+            "INTERVAL" => rule.interval = number()?.max(1),
+            "COUNT" => rule.count = Some(number()?),
+            "UNTIL" => rule.until = Some(parse_ics_date(val)?),
+            "BYDAY" => {
+                rule.by_day =
+                    val.split(',').map(parse_by_day).collect::<Result<_>>()?
+            }
+            "BYMONTH" => {
+                rule.by_month = val
+                    .split(',')
+                    .map(|m| match m.parse() {
+                        Ok(m @ 1..=12) => Ok(m),
+                        _ => bail!(r#"invalid RRULE part "{part}""#),
+                    })
+                    .collect::<Result<_>>()?
+            }
+            "WKST" => (),
+            _ => bail!(r#"unsupported RRULE part "{key}""#),
+        }
+    }
+    rule.freq = freq.ok_or_else(|| anyhow!("RRULE without FREQ"))?;
+    Ok(rule)
+}
+
+// --------------------------------------------------
+// MO, 2MO, -1FR
+fn parse_by_day(value: &str) -> Result<(Option<i32>, Weekday)> {
+    if !value.is_ascii() {
+        bail!(r#"invalid BYDAY "{value}""#);
+    }
+    let split = value.len().saturating_sub(2);
+    let (ord, day) = value.split_at(split);
+    let weekday = match day {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => bail!(r#"invalid BYDAY "{value}""#),
+    };
+    let ord = match ord {
+        "" => None,
+        ord => Some(
+            ord.trim_start_matches('+')
+                .parse()
+                .map_err(|_| anyhow!(r#"invalid BYDAY "{value}""#))?,
+        ),
+    };
+    Ok((ord, weekday))
+}
+
+impl Event {
+    // --------------------------------------------------
+    // from 到 to(含)之间发生的日期
+    pub fn occurrences(
+        &self,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Vec<NaiveDate> {
+        let Some(rule) = &self.rule else {
+            return vec![self.start]
+                .into_iter()
+                .filter(|d| (from..=to).contains(d))
+                .collect();
+        };
+        let last = rule.until.map_or(to, |until| until.min(to));
+        let mut dates = vec![];
+        let mut count = 0;
+
+        // 逐个周期生成候选日期, COUNT 从第一次发生开始计数
+        for period in 0.. {
+            let Some((period_start, mut candidates)) =
+                self.period(rule, period)
+            else {
+                break;
+            };
+            if period_start > last {
+                break;
+            }
+            candidates.sort();
+            for date in candidates.into_iter().filter(|d| *d >= self.start) {
+                if date > last || rule.count.is_some_and(|max| count >= max) {
+                    return dates;
+                }
+                count += 1;
+                if date >= from {
+                    dates.push(date);
+                }
+            }
+        }
+        dates
+    }
+
+    // --------------------------------------------------
+    // 第 n 个周期的起始日期和其中的候选日期
+    fn period(
+        &self,
+        rule: &Rule,
+        n: u32,
+    ) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let step = n.checked_mul(rule.interval)?;
+        match rule.freq {
+            Freq::Daily => {
+                let date =
+                    self.start.checked_add_days(Days::new(step as u64))?;
+                Some((date, vec![date]))
+            }
+            Freq::Weekly => {
+                let monday = self.start
+                    - Days::new(
+                        self.start.weekday().num_days_from_monday() as u64
+                    );
+                let week =
+                    monday.checked_add_days(Days::new(step as u64 * 7))?;
+                let days = match rule.by_day.is_empty() {
+                    true => vec![self.start.weekday()],
+                    false => rule.by_day.iter().map(|&(_, day)| day).collect(),
+                };
+                let dates = days
+                    .iter()
+                    .map(|day| {
+                        week + Days::new(day.num_days_from_monday() as u64)
+                    })
+                    .collect();
+                Some((week, dates))
+            }
+            Freq::Monthly => {
+                let month = self
+                    .start
+                    .with_day(1)?
+                    .checked_add_months(Months::new(step))?;
+                Some((month, self.month_candidates(rule, month)))
+            }
+            Freq::Yearly => {
+                let year = NaiveDate::from_ymd_opt(
+                    self.start.year() + step as i32,
+                    1,
+                    1,
+                )?;
+                let months = match rule.by_month.is_empty() {
+                    true => vec![self.start.month()],
+                    false => rule.by_month.clone(),
+                };
+                let dates = months
+                    .iter()
+                    .filter_map(|&m| year.with_month(m))
+                    .flat_map(|month| self.month_candidates(rule, month))
+                    .collect();
+                Some((year, dates))
+            }
+        }
+    }
+
+    // --------------------------------------------------
+    // 某月中的候选日期: 没有 BYDAY 时为与起始日期相同的日子(不存在则跳过)
+    fn month_candidates(
+        &self,
+        rule: &Rule,
+        month: NaiveDate,
+    ) -> Vec<NaiveDate> {
+        if rule.by_day.is_empty() {
+            return month.with_day(self.start.day()).into_iter().collect();
+        }
+        let days: Vec<NaiveDate> = month
+            .iter_days()
+            .take_while(|d| d.month() == month.month())
+            .collect();
+        rule.by_day
+            .iter()
+            .flat_map(|&(ord, weekday)| {
+                let matching: Vec<NaiveDate> = days
+                    .iter()
+                    .copied()
+                    .filter(|d| d.weekday() == weekday)
+                    .collect();
+                match ord {
+                    None => matching,
+                    Some(n) if n > 0 => matching
+                        .get(n as usize - 1)
+                        .copied()
+                        .into_iter()
+                        .collect(),
+                    Some(n) => matching
+                        .len()
+                        .checked_sub(n.unsigned_abs() as usize)
+                        .and_then(|i| matching.get(i).copied())
+                        .into_iter()
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
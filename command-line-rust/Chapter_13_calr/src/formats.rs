@@ -0,0 +1,110 @@
+// --format json|html|ics: 与终端月历相同的月份结构的其他输出方式
+use crate::Month;
+use serde::Serialize;
+use std::fmt::Write;
+
+// JSON 输出的顶层结构, 显示整年时带有 year
+#[derive(Debug, Serialize)]
+pub struct Calendar<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>,
+    pub months: &'a [Month],
+}
+
+// --------------------------------------------------
+// 每个月一个 <table>, 今天和有事件的日子用 class 标出
+pub fn html(year: Option<i32>, months: &[Month]) -> String {
+    let mut out = String::new();
+    if let Some(year) = year {
+        writeln!(out, r#"<div class="year"><h1>{year}</h1>"#).unwrap();
+    }
+    for month in months {
+        let caption = match year {
+            Some(_) => month.name.to_string(),
+            None => format!("{} {}", month.name, month.year),
+        };
+        writeln!(out, r#"<table class="month">"#).unwrap();
+        writeln!(out, "<caption>{caption}</caption>").unwrap();
+        let names: String =
+            month.weekdays.iter().map(|name| format!("<th>{name}</th>")).collect();
+        writeln!(out, "<thead><tr>{names}</tr></thead>").unwrap();
+        writeln!(out, "<tbody>").unwrap();
+        for week in &month.weeks {
+            let mut row = String::new();
+            for day in &week.days {
+                let Some(day) = day else {
+                    row.push_str("<td></td>");
+                    continue;
+                };
+                let mut classes = vec![];
+                if day.today {
+                    classes.push("today");
+                }
+                if !day.events.is_empty() {
+                    classes.push("event");
+                }
+                let class = match classes.is_empty() {
+                    true => String::new(),
+                    false => format!(r#" class="{}""#, classes.join(" ")),
+                };
+                let summaries: Vec<&str> = day
+                    .events
+                    .iter()
+                    .map(|&idx| month.events[idx].summary.as_str())
+                    .collect();
+                let title = match summaries.is_empty() {
+                    true => String::new(),
+                    false => format!(r#" title="{}""#, escape(&summaries.join("; "))),
+                };
+                write!(row, "<td{class}{title}>{}</td>", day.day).unwrap();
+            }
+            writeln!(out, "<tr>{row}</tr>").unwrap();
+        }
+        writeln!(out, "</tbody>").unwrap();
+        writeln!(out, "</table>").unwrap();
+    }
+    if year.is_some() {
+        writeln!(out, "</div>").unwrap();
+    }
+    out
+}
+
+// --------------------------------------------------
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// --------------------------------------------------
+// 显示范围内每次发生的事件作为一个全天的 VEVENT, 行以 CRLF 结尾
+pub fn ics(months: &[Month]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//calr//EN".to_string(),
+    ];
+    for month in months {
+        for event in &month.events {
+            for date in &event.dates {
+                let date = date.replace('-', "");
+                lines.push("BEGIN:VEVENT".to_string());
+                lines.push(format!("UID:{date}-{}@calr", event.id));
+                lines.push(format!("DTSTAMP:{date}T000000Z"));
+                lines.push(format!("DTSTART;VALUE=DATE:{date}"));
+                lines.push(format!("SUMMARY:{}", escape_ics(&event.summary)));
+                lines.push("END:VEVENT".to_string());
+            }
+        }
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| format!("{line}\r\n")).collect()
+}
+
+// --------------------------------------------------
+fn escape_ics(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+}
@@ -1,11 +1,19 @@
-use ansi_term::Style;       // 终端样式和颜色
+use ansi_term::{Colour, Style};  // 终端样式和颜色
 use anyhow::{bail, Result};  // bail!()包装错误 // Result 类型
-use chrono::{Datelike, Local, NaiveDate};  // 日期处理
-use clap::Parser;
-use itertools::izip;  // 与标准库的 zip 相比,izip! 允许一次合并多于两个的迭代器
+mod calendar;
+mod events;
+mod formats;
+
+use calendar::Reform;
+use events::Event;
+use chrono::{Datelike, Local, NaiveDate, Weekday};  // 日期处理
+use clap::{Parser, ValueEnum};
+use serde::Serialize;
+use std::{env, io::Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr}; // 按显示宽度截断说明
 
 // cargo add  clap --features derive
-// cargo add ansi_term anyhow chrono itertools
+// cargo add ansi_term anyhow chrono
 
 // cargo add --dev assert_cmd predicates pretty_assertions rand sys_info
 
@@ -25,9 +33,122 @@ struct Args {
     /// Show the whole current year
     #[arg(short('y'), long("year"), conflicts_with_all(["month", "year"]))]
     show_current_year: bool,  // 显示当前年份标志
+
+    /// Start weeks on Monday (default: from the locale)
+    #[arg(long, conflicts_with("sunday"))]
+    monday: bool,
+
+    /// Start weeks on Sunday (default: from the locale)
+    #[arg(long)]
+    sunday: bool,
+
+    /// Show ISO week numbers
+    #[arg(short('w'), long("week-numbers"))]
+    week_numbers: bool,
+
+    /// Show the previous, current and next month
+    #[arg(short('3'))]
+    three: bool,
+
+    /// Also show N months after the target month
+    #[arg(short('A'), value_name = "N", default_value_t = 0)]
+    after: u32,
+
+    /// Also show N months before the target month
+    #[arg(short('B'), value_name = "N", default_value_t = 0)]
+    before: u32,
+
+    /// Show N months starting with the target month
+    #[arg(short('n'), long("months"), value_name = "N",
+          value_parser(clap::value_parser!(u32).range(1..)))]
+    months: Option<u32>,
+
+    /// Number of months per row [default: 3, or 2 with -j]
+    #[arg(short('c'), long("columns"), value_name = "N",
+          value_parser(clap::value_parser!(u16).range(1..)))]
+    columns: Option<u16>,
+
+    /// Show day-of-year numbers instead of days of the month
+    #[arg(short('j'), long("julian"))]
+    day_of_year: bool,
+
+    /// Calendar system to use
+    #[arg(long, value_enum, default_value = "1752")]
+    reform: Reform,
+
+    /// Highlight events from an iCalendar (.ics) or TOML (.toml) file
+    #[arg(long, value_name = "FILE")]
+    events: Vec<String>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value = "text")]
+    format: Format,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text, // 终端中的月历
+    Json,
+    Html,
+    Ics, // 只包含事件的 iCalendar
+}
+
+// 影响月份排版的选项
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    first_weekday: Weekday, // 每行从星期几开始
+    week_numbers: bool,     // 左侧显示 ISO 周数
+    day_of_year: bool,      // -j: 显示一年中的第几天, 每格 3 个字符
+    reform: Reform,         // 历法
+}
+
+const WEEK_WIDTH: usize = 3; // 周数栏 "NN "
+// 一个月的结构, 由 build_month 计算
+#[derive(Debug, Serialize)]
+struct Month {
+    year: i32,
+    month: u32,
+    name: &'static str,
+    weekdays: Vec<&'static str>, // 从一周的第一天开始
+    weeks: Vec<Week>,
+    events: Vec<MonthEvent>,
+}
+
+#[derive(Debug, Serialize)]
+struct Week {
+    iso_week: Option<u32>,
+    days: Vec<Option<Day>>, // 七个格子, 月初和月末的空格子为 None
+}
+
+#[derive(Debug, Serialize)]
+struct Day {
+    day: u32,
+    day_of_year: i64,
+    weekday: &'static str,
+    today: bool,
+    events: Vec<usize>, // 在 Month::events 中的下标
+}
+
+// 本月发生的一个事件
+#[derive(Debug, Serialize)]
+struct MonthEvent {
+    #[serde(skip)]
+    id: usize, // 在事件文件中的序号, 决定颜色
+    summary: String,
+    days: Vec<u32>,     // 月中的日期
+    dates: Vec<String>, // 格里高利历日期 YYYY-MM-DD
 }
 
-const LINE_WIDTH: usize = 22;
+// 事件依次使用的颜色
+const EVENT_COLOURS: [Colour; 6] = [
+    Colour::Red,
+    Colour::Green,
+    Colour::Yellow,
+    Colour::Blue,
+    Colour::Purple,
+    Colour::Cyan,
+];
+const WEEKDAY_NAMES: [&str; 7] = ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"];
 const MONTH_NAMES: [&str; 12] = [
     "January",
     "February",
@@ -45,10 +166,7 @@ const MONTH_NAMES: [&str; 12] = [
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
-    }
+    clr_core::exit(run(Args::parse()));
 }
 
 // --------------------------------------------------
@@ -68,33 +186,137 @@ fn run(args: Args) -> Result<()> {
     }
     let year = year.unwrap_or(today.year());
 
-    match month {
-        Some(month) => {
-            let lines = format_month(year, month, true, today);
-            println!("{}", lines.join("\n"));
-        }
-        None => {
-            println!("{year:>32}");
-            let months: Vec<_> = (1..=12)
-                .map(|month| format_month(year, month, false, today))
-                .collect();
+    let mut events = vec![];
+    for path in &args.events {
+        events.extend(events::load(path)?);
+    }
 
-            for (i, chunk) in months.chunks(3).enumerate() {
-                if let [m1, m2, m3] = chunk {
-                    for lines in izip!(m1, m2, m3) {
-                        println!("{}{}{}", lines.0, lines.1, lines.2);
-                    }
-                    if i < 3 {
-                        println!();
-                    }
-                }
-            }
+    let layout = Layout {
+        first_weekday: match (args.monday, args.sunday) {
+            (true, _) => Weekday::Mon,
+            (_, true) => Weekday::Sun,
+            _ => locale_first_weekday(),
+        },
+        week_numbers: args.week_numbers,
+        day_of_year: args.day_of_year,
+        reform: args.reform,
+    };
+    // -j 时每个月更宽, 默认每行少放一个月
+    let columns = args
+        .columns
+        .unwrap_or(if args.day_of_year { 2 } else { 3 }) as usize;
+
+    // 要显示的月份范围, 以 年*12+月 的序号表示; 只给出年份时从一月开始显示全年
+    let (before, after) = match args.three {
+        true => (args.before.max(1), args.after.max(1)),
+        false => (args.before, args.after),
+    };
+    let count = args.months.unwrap_or(if month.is_some() { 1 } else { 12 });
+    let target = year * 12 + month.unwrap_or(1) as i32 - 1;
+    // 不超出命令行接受的年份范围 1..=9999
+    let (min, max) = (12, 9999 * 12 + 11);
+    let first = (target - before as i32).clamp(min, max);
+    let last = (target + (count + after) as i32 - 1).clamp(min, max);
+    let whole_year = month.is_none() && first == target && last == target + 11;
+
+    let months: Vec<Month> = (first..=last)
+        .map(|idx| {
+            let (year, month) = (idx.div_euclid(12), idx.rem_euclid(12) as u32 + 1);
+            build_month(year, month, today, layout, &events)
+        })
+        .collect();
+
+    let mut out = clr_core::stdout();
+    match args.format {
+        Format::Text => (),
+        Format::Json => {
+            let calendar = formats::Calendar {
+                year: whole_year.then_some(year),
+                months: &months,
+            };
+            writeln!(out, "{}", serde_json::to_string_pretty(&calendar)?)?;
+            return Ok(out.flush()?);
         }
+        Format::Html => {
+            let html = formats::html(whole_year.then_some(year), &months);
+            write!(out, "{html}")?;
+            return Ok(out.flush()?);
+        }
+        Format::Ics => {
+            write!(out, "{}", formats::ics(&months))?;
+            return Ok(out.flush()?);
+        }
+    }
+
+    if first == last {
+        let lines = format_month(&months[0], true, layout);
+        writeln!(out, "{}", lines.join("\n"))?;
+        return Ok(out.flush()?);
+    }
+
+    if whole_year {
+        let width = month_width(layout) * columns;
+        writeln!(out, "{year:>w$}", w = width / 2 - 1)?;
     }
+    let months: Vec<_> = months
+        .iter()
+        .map(|month| format_month(month, !whole_year, layout))
+        .collect();
 
+    // 每行 columns 个月, 逐行拼接各月的同一行; 事件说明的行数不同, 短的补空行
+    let rows: Vec<_> = months.chunks(columns).collect();
+    let blank = " ".repeat(month_width(layout));
+    for (i, row) in rows.iter().enumerate() {
+        let height = row.iter().map(Vec::len).max().unwrap_or(0);
+        for line in 0..height {
+            let line: String = row
+                .iter()
+                .map(|m| m.get(line).unwrap_or(&blank).as_str())
+                .collect();
+            writeln!(out, "{line}")?;
+        }
+        if i + 1 < rows.len() {
+            writeln!(out)?;
+        }
+    }
+    out.flush()?;
     Ok(())
 }
 
+// --------------------------------------------------
+// 从 LC_ALL/LC_TIME/LANG 的地区部分(如 en_US 中的 US)判断一周的第一天,
+// 地区未知时(包括 C 和 POSIX)从星期日开始
+fn locale_first_weekday() -> Weekday {
+    // CLDR 中一周从星期日开始的地区
+    const SUNDAY: [&str; 36] = [
+        "AG", "AS", "BR", "BS", "BT", "BW", "BZ", "CA", "CO", "DM", "DO", "GT",
+        "GU", "HK", "HN", "IL", "IN", "JM", "JP", "KE", "KH", "KR", "MO", "MX",
+        "NI", "PA", "PE", "PH", "PR", "PY", "SG", "SV", "TW", "US", "VE", "ZA",
+    ];
+    // 一周从星期六开始的地区
+    const SATURDAY: [&str; 10] =
+        ["AE", "AF", "BH", "DZ", "EG", "IQ", "JO", "KW", "LY", "QA"];
+
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|val| !val.is_empty())
+        .unwrap_or_default();
+    // 形如 language_TERRITORY.codeset@modifier
+    let territory = locale
+        .split(['.', '@'])
+        .next()
+        .and_then(|name| name.split_once('_'))
+        .map(|(_, territory)| territory.to_uppercase());
+
+    match territory.as_deref() {
+        None => Weekday::Sun,
+        Some(t) if SUNDAY.contains(&t) => Weekday::Sun,
+        Some(t) if SATURDAY.contains(&t) => Weekday::Sat,
+        Some(_) => Weekday::Mon,
+    }
+}
+
 // --------------------------------------------------
 fn parse_month(month: String) -> Result<u32> {
     match month.parse() {
@@ -131,58 +353,132 @@ fn parse_month(month: String) -> Result<u32> {
 }
 
 // --------------------------------------------------
-fn last_day_in_month(year: i32, month: u32) -> NaiveDate {
-    // 计算下个月的第一天
-    // The first day of the next month...
-    let (y, m) = if month == 12 {
-        (year + 1, 1)
-    } else {
-        (year, month + 1)
-    };
+// 每个日期格的宽度
+fn cell_width(layout: Layout) -> usize {
+    if layout.day_of_year { 3 } else { 2 }
+}
 
-    // ...is preceded by the last day of the original month
-    NaiveDate::from_ymd_opt(y, m, 1)
-        .unwrap()
-        .pred_opt() // 获取前一天的日期
-        .unwrap()
+// --------------------------------------------------
+// 七个日期格及其间隔的宽度, 不含周数栏和右侧的两个空格
+fn grid_width(layout: Layout) -> usize {
+    7 * cell_width(layout) + 6
 }
 
 // --------------------------------------------------
-fn format_month(
+// 一个月占用的宽度, 包括右侧的两个空格
+fn month_width(layout: Layout) -> usize {
+    grid_width(layout) + 2 + if layout.week_numbers { WEEK_WIDTH } else { 0 }
+}
+
+// --------------------------------------------------
+// 计算一个月的结构: 每周的七个格子、日期、星期、今天和事件,
+// 文本、JSON、HTML 和 iCalendar 输出都由它生成
+fn build_month(
     year: i32,
     month: u32,
-    print_year: bool,
     today: NaiveDate,
-) -> Vec<String> {
-    let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
-
-    // 生成日期前的空白（对齐星期）
-    let mut days: Vec<String> = (1..first.weekday().number_from_sunday())
-        .map(|_| "  ".to_string()) // two spaces
-        .collect();
-
-    // 检查是否是今天的日期
-    let is_today = |day: u32| {
-        year == today.year() && month == today.month() && day == today.day()
+    layout: Layout,
+    events: &[Event],
+) -> Month {
+    let month_days = layout.reform.month_days(year, month);
+    let first = month_days[0].jdn;
+    let last = month_days.last().unwrap().jdn;
+    let start = layout.first_weekday.num_days_from_sunday();
+    let offset = (calendar::weekday_from_sunday(first) + 7 - start) % 7;
+
+    // 按儒略日数比较, 不同历法下也能找到今天
+    let today = calendar::gregorian_jdn(today);
+
+    // 本月中每个事件发生的日期(按儒略日数), 事件文件中的日期都是格里高利历
+    let (from, to) = (calendar::to_gregorian(first), calendar::to_gregorian(last));
+    let occurrences: Vec<(usize, Vec<NaiveDate>)> = match (from, to) {
+        (Some(from), Some(to)) => events
+            .iter()
+            .enumerate()
+            .map(|(i, event)| (i, event.occurrences(from, to)))
+            .filter(|(_, dates)| !dates.is_empty())
+            .collect(),
+        _ => vec![],
     };
+    let day_of = |date: &NaiveDate| {
+        let jdn = calendar::gregorian_jdn(*date);
+        month_days.iter().find(|d| d.jdn == jdn).map_or(0, |d| d.day)
+    };
+    let month_events = occurrences
+        .iter()
+        .map(|(i, dates)| MonthEvent {
+            id: *i,
+            summary: events[*i].summary.clone(),
+            days: dates.iter().map(day_of).collect(),
+            dates: dates.iter().map(|d| d.format("%Y-%m-%d").to_string()).collect(),
+        })
+        .collect();
 
-    // 添加所有日期，高亮今天
-    let last = last_day_in_month(year, month);
-    days.extend((first.day()..=last.day()).map(|num| {
-        let fmt = format!("{num:>2}"); // 右对齐两位数字
-        if is_today(num) {
-            Style::new().reverse().paint(fmt).to_string() // 反色显示今天
-        } else {
-            fmt
-        }
+    // 日期前后的空格子为 None
+    let mut cells: Vec<Option<Day>> = (0..offset).map(|_| None).collect();
+    cells.extend(month_days.iter().map(|day| {
+        let date = calendar::to_gregorian(day.jdn);
+        Some(Day {
+            day: day.day,
+            day_of_year: layout.reform.day_of_year(year, day.jdn),
+            weekday: WEEKDAY_NAMES[calendar::weekday_from_sunday(day.jdn) as usize],
+            today: day.jdn == today,
+            events: occurrences
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, dates))| date.is_some_and(|d| dates.contains(&d)))
+                .map(|(idx, _)| idx)
+                .collect(),
+        })
     }));
+    while !cells.len().is_multiple_of(7) {
+        cells.push(None);
+    }
 
-    let month_name = MONTH_NAMES[month as usize - 1];
+    // 第一行的第一格对应的儒略日数, 改历跳过的日期不影响星期的推算
+    let row_start = first - offset as i64;
+    let mut chunks = cells.into_iter().peekable();
+    let mut weeks = vec![];
+    while chunks.peek().is_some() {
+        // 以该行中的星期一所在的 ISO 周为准
+        let to_monday = (8 - start as i64) % 7;
+        let monday = row_start + weeks.len() as i64 * 7 + to_monday;
+        weeks.push(Week {
+            iso_week: calendar::iso_week(monday).map(|w| w.week()),
+            days: chunks.by_ref().take(7).collect(),
+        });
+    }
+
+    Month {
+        year,
+        month,
+        name: MONTH_NAMES[month as usize - 1],
+        weekdays: (0..7)
+            .map(|i| WEEKDAY_NAMES[(start as usize + i) % 7])
+            .collect(),
+        weeks,
+        events: month_events,
+    }
+}
+
+// --------------------------------------------------
+fn format_month(month: &Month, print_year: bool, layout: Layout) -> Vec<String> {
+    let cell = cell_width(layout);
+    let grid = grid_width(layout);
+    let number = |day: &Day| match layout.day_of_year {
+        true => day.day_of_year,
+        false => day.day as i64,
+    };
+
+    let month_name = month.name;
+    let year = month.year;
     let mut lines = Vec::with_capacity(8); // 预分配空间,8行
+    // 有周数栏时, 标题和星期行前面留出同样的宽度
+    let gutter = if layout.week_numbers { " ".repeat(WEEK_WIDTH) } else { String::new() };
 
     // 月份标题行
     lines.push(format!(
-        "{:^20}  ", // two trailing spaces
+        "{gutter}{:^grid$}  ", // two trailing spaces
         if print_year {
             format!("{month_name} {year}")
         } else {
@@ -190,27 +486,94 @@ fn format_month(
         }
     ));
 
-    lines.push("Su Mo Tu We Th Fr Sa  ".to_string()); // two trailing spaces
-
-    for week in days.chunks(7) {
-        lines.push(format!(
-            "{:width$}  ", // two trailing spaces
-            week.join(" "),
-            width = LINE_WIDTH - 2
-        ));
+    let weekdays: Vec<_> =
+        month.weekdays.iter().map(|name| format!("{name:>cell$}")).collect();
+    lines.push(format!("{gutter}{}  ", weekdays.join(" "))); // two trailing spaces
+
+    for week in &month.weeks {
+        let week_number = match (layout.week_numbers, week.iso_week) {
+            (false, _) => String::new(),
+            (true, Some(num)) => format!("{num:>2} "),
+            (true, None) => " ".repeat(WEEK_WIDTH),
+        };
+        // 添加所有日期，高亮今天和有事件的日子
+        let days: Vec<String> = week
+            .days
+            .iter()
+            .map(|day| match day {
+                None => " ".repeat(cell),
+                Some(day) => {
+                    let fmt = format!("{:>cell$}", number(day)); // 右对齐
+                    let mut style = Style::new();
+                    // 同一天有多个事件时使用第一个事件的颜色
+                    if let Some(&idx) = day.events.first() {
+                        style = style.fg(event_colour(month.events[idx].id)).bold();
+                    }
+                    if day.today {
+                        style = style.reverse(); // 反色显示今天
+                    }
+                    if style == Style::new() {
+                        fmt
+                    } else {
+                        style.paint(fmt).to_string()
+                    }
+                }
+            })
+            .collect();
+        lines.push(format!("{week_number}{}  ", days.join(" "))); // two trailing spaces
     }
 
     while lines.len() < 8 {
-        lines.push(" ".repeat(LINE_WIDTH));
+        lines.push(" ".repeat(month_width(layout)));
+    }
+
+    // 在月历下方列出本月的事件: 日期 说明, 过长时截断
+    let width = month_width(layout) - 2;
+    for event in &month.events {
+        let days: Vec<String> = event
+            .days
+            .iter()
+            .filter_map(|&num| month.day(num))
+            .map(|day| number(day).to_string())
+            .collect();
+        let legend = format!("{:>2} {}", days.join(","), event.summary);
+        // 按显示宽度截断和补齐, 中文等全角字符占两列
+        let mut used = 0;
+        let legend: String = legend
+            .chars()
+            .take_while(|c| {
+                used += c.width().unwrap_or(0);
+                used <= width
+            })
+            .collect();
+        let padding = " ".repeat(width - legend.width());
+        let colour = event_colour(event.id);
+        lines.push(format!("{}  ", colour.paint(legend + &padding)));
     }
 
     lines
 }
 
+// --------------------------------------------------
+// 事件依次使用调色板中的颜色
+fn event_colour(id: usize) -> Colour {
+    EVENT_COLOURS[id % EVENT_COLOURS.len()]
+}
+
+impl Month {
+    // --------------------------------------------------
+    fn day(&self, num: u32) -> Option<&Day> {
+        self.weeks
+            .iter()
+            .flat_map(|week| week.days.iter().flatten())
+            .find(|day| day.day == num)
+    }
+}
+
 // --------------------------------------------------
 // #[cfg(test)]
 // mod tests {
-//     use super::{format_month, last_day_in_month, parse_month};
+//     use super::{format_month, parse_month};
 //     use chrono::NaiveDate;
 
 //     #[test]
@@ -286,20 +649,4 @@ fn format_month(
 //         let today = NaiveDate::from_ymd_opt(2021, 4, 7).unwrap();
 //         assert_eq!(format_month(2021, 4, true, today), april_hl);
 //     }
-
-//     #[test]
-//     fn test_last_day_in_month() {
-//         assert_eq!(
-//             last_day_in_month(2020, 1),
-//             NaiveDate::from_ymd_opt(2020, 1, 31).unwrap()
-//         );
-//         assert_eq!(
-//             last_day_in_month(2020, 2),
-//             NaiveDate::from_ymd_opt(2020, 2, 29).unwrap()
-//         );
-//         assert_eq!(
-//             last_day_in_month(2020, 4),
-//             NaiveDate::from_ymd_opt(2020, 4, 30).unwrap()
-//         );
-//     }
 // }
@@ -6,10 +6,18 @@ use std::fs;
 
 const PRG: &str = "calr";
 
+// --------------------------------------------------
+// 一周的第一天取决于地区设置, 测试中固定为 C(从星期日开始)
+fn calr() -> Result<Command> {
+    let mut cmd = Command::cargo_bin(PRG)?;
+    cmd.env_remove("LC_ALL").env_remove("LC_TIME").env("LANG", "C");
+    Ok(cmd)
+}
+
 // --------------------------------------------------
 #[test]
 fn dies_year_0() -> Result<()> {
-    Command::cargo_bin(PRG)?.arg("0").assert().failure().stderr(
+    calr()?.arg("0").assert().failure().stderr(
         predicate::str::contains(
             "error: invalid value '0' for '[YEAR]': 0 is not in 1..=9999",
         ),
@@ -20,7 +28,7 @@ fn dies_year_0() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn dies_year_10000() -> Result<()> {
-    Command::cargo_bin(PRG)?
+    calr()?
         .arg("10000")
         .assert()
         .failure()
@@ -34,7 +42,7 @@ fn dies_year_10000() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn dies_invalid_year() -> Result<()> {
-    Command::cargo_bin(PRG)?
+    calr()?
         .arg("foo")
         .assert()
         .failure()
@@ -48,7 +56,7 @@ fn dies_invalid_year() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn dies_month_0() -> Result<()> {
-    let output = Command::cargo_bin(PRG)?
+    let output = calr()?
         .args(["-m", "0"])
         .output()
         .expect("fail");
@@ -58,7 +66,7 @@ fn dies_month_0() -> Result<()> {
     assert_eq!(stdout, "");
 
     let stderr = String::from_utf8(output.stderr).expect("invalid UTF-8");
-    assert_eq!(stderr.trim(), r#"month "0" not in the range 1 through 12"#);
+    assert_eq!(stderr.trim(), r#"calr: month "0" not in the range 1 through 12"#);
 
     Ok(())
 }
@@ -66,28 +74,28 @@ fn dies_month_0() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn dies_month_13() -> Result<()> {
-    let output = Command::cargo_bin(PRG)?
+    let output = calr()?
         .args(["-m", "13"])
         .output()
         .expect("fail");
     assert!(!output.status.success());
 
     let stderr = String::from_utf8(output.stderr).expect("invalid UTF-8");
-    assert_eq!(stderr.trim(), r#"month "13" not in the range 1 through 12"#);
+    assert_eq!(stderr.trim(), r#"calr: month "13" not in the range 1 through 12"#);
     Ok(())
 }
 
 // --------------------------------------------------
 #[test]
 fn dies_invalid_month() -> Result<()> {
-    let output = Command::cargo_bin(PRG)?
+    let output = calr()?
         .args(["-m", "foo"])
         .output()
         .expect("fail");
     assert!(!output.status.success());
 
     let stderr = String::from_utf8(output.stderr).expect("invalid UTF-8");
-    assert_eq!(stderr.trim(), r#"Invalid month "foo""#);
+    assert_eq!(stderr.trim(), r#"calr: Invalid month "foo""#);
     Ok(())
 }
 
@@ -95,7 +103,7 @@ fn dies_invalid_month() -> Result<()> {
 #[test]
 fn dies_y_and_month() -> Result<()> {
     let expected = "the argument '-m <MONTH>' cannot be used with '--year'";
-    Command::cargo_bin(PRG)?
+    calr()?
         .args(["-m", "1", "-y"])
         .assert()
         .failure()
@@ -107,7 +115,7 @@ fn dies_y_and_month() -> Result<()> {
 #[test]
 fn dies_y_and_year() -> Result<()> {
     let expected = "the argument '--year' cannot be used with '[YEAR]'";
-    Command::cargo_bin(PRG)?
+    calr()?
         .args(["-y", "2000"])
         .assert()
         .failure()
@@ -134,7 +142,7 @@ fn month_num() -> Result<()> {
     ];
 
     for (num, month) in expected {
-        Command::cargo_bin(PRG)?
+        calr()?
             .args(["-m", num])
             .assert()
             .success()
@@ -161,7 +169,7 @@ fn partial_month() -> Result<()> {
     ];
 
     for (arg, month) in expected {
-        Command::cargo_bin(PRG)?
+        calr()?
             .args(["-m", arg])
             .assert()
             .success()
@@ -173,7 +181,7 @@ fn partial_month() -> Result<()> {
 // --------------------------------------------------
 fn run(args: &[&str], expected_file: &str) -> Result<()> {
     let expected = fs::read_to_string(expected_file)?;
-    let output = Command::cargo_bin(PRG)?.args(args).output().expect("fail");
+    let output = calr()?.args(args).output().expect("fail");
     assert!(output.status.success());
 
     let stdout = String::from_utf8(output.stdout).expect("invalid UTF-8");
@@ -184,7 +192,7 @@ fn run(args: &[&str], expected_file: &str) -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn default_one_month() -> Result<()> {
-    let cmd = Command::cargo_bin(PRG)?.assert().success();
+    let cmd = calr()?.assert().success();
     let out = cmd.get_output();
     let stdout = String::from_utf8(out.stdout.clone())?;
     let lines: Vec<_> = stdout.split('\n').collect();
@@ -220,9 +228,439 @@ fn test_2020() -> Result<()> {
 // --------------------------------------------------
 #[test]
 fn year() -> Result<()> {
-    let cmd = Command::cargo_bin(PRG)?.arg("-y").assert().success();
+    let cmd = calr()?.arg("-y").assert().success();
     let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
     let lines: Vec<&str> = stdout.split('\n').collect();
     assert_eq!(lines.len(), 37);
     Ok(())
 }
+
+// --------------------------------------------------
+#[test]
+fn monday_week_numbers() -> Result<()> {
+    let expected = "       January 2021      \n\
+                    \x20  Mo Tu We Th Fr Sa Su  \n\
+                    53              1  2  3  \n\
+                    \x201  4  5  6  7  8  9 10  \n\
+                    \x202 11 12 13 14 15 16 17  \n\
+                    \x203 18 19 20 21 22 23 24  \n\
+                    \x204 25 26 27 28 29 30 31  \n\
+                    \x20                        \n";
+    calr()?
+        .args(["--monday", "-w", "-m", "1", "2021"])
+        .assert()
+        .success()
+        .stdout(expected);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn locale_first_weekday() -> Result<()> {
+    for (lang, header) in [
+        ("de_DE.UTF-8", "Mo Tu We Th Fr Sa Su"),
+        ("en_US.UTF-8", "Su Mo Tu We Th Fr Sa"),
+        ("C", "Su Mo Tu We Th Fr Sa"),
+    ] {
+        calr()?
+            .env("LANG", lang)
+            .args(["-m", "3", "2026"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(header));
+    }
+    // LC_TIME 优先于 LANG, LC_ALL 优先于两者
+    calr()?
+        .env("LANG", "de_DE.UTF-8")
+        .env("LC_TIME", "en_US.UTF-8")
+        .args(["-m", "3", "2026"])
+        .assert()
+        .stdout(predicate::str::contains("Su Mo Tu We Th Fr Sa"));
+    calr()?
+        .env("LC_TIME", "en_US.UTF-8")
+        .env("LC_ALL", "de_DE.UTF-8")
+        .args(["-m", "3", "2026"])
+        .assert()
+        .stdout(predicate::str::contains("Mo Tu We Th Fr Sa Su"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn three_months() -> Result<()> {
+    let cmd = calr()?
+        .args(["--sunday", "-3", "-m", "1", "2021"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(
+        lines[0],
+        "   December 2020          January 2021         February 2021      "
+    );
+    assert_eq!(lines[7], format!("{:22}31{:42}", "", ""));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn months_in_rows() -> Result<()> {
+    // 5 个月, 每行 2 个: 3 行, 行之间空一行
+    let cmd = calr()?
+        .args(["-n", "5", "-c", "2", "-m", "11", "2020"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 26);
+    assert_eq!(lines[0], "   November 2020         December 2020      ");
+    assert_eq!(lines[9], "    January 2021         February 2021      ");
+    assert_eq!(lines[18], "     March 2021       ");
+
+    // -B/-A 与 -n 一起使用
+    let cmd = calr()?
+        .args(["-B", "2", "-A", "1", "-c", "4", "-m", "1", "2021"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert!(stdout.starts_with("   November 2020  "));
+    assert!(stdout.lines().next().unwrap().ends_with("February 2021      "));
+
+    // 范围在第 1 年和第 9999 年处截止
+    let first_line = |args: &[&str]| -> Result<String> {
+        let output = calr()?.args(args).output()?;
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout)?;
+        Ok(stdout.lines().next().unwrap_or_default().trim().to_string())
+    };
+    assert_eq!(first_line(&["-m", "1", "1", "-B", "2"])?, "January 1");
+    assert_eq!(
+        first_line(&["-m", "12", "9999", "-A", "2", "-B", "1"])?,
+        "November 9999         December 9999"
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+fn run_stdout(args: &[&str], expected: &str) -> Result<()> {
+    calr()?
+        .args(args)
+        .assert()
+        .success()
+        .stdout(expected.to_string());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn reform_1752() -> Result<()> {
+    let expected = "   September 1752     \n\
+                    Su Mo Tu We Th Fr Sa  \n\
+                    \x20      1  2 14 15 16  \n\
+                    17 18 19 20 21 22 23  \n\
+                    24 25 26 27 28 29 30  \n\
+                    \x20                     \n\
+                    \x20                     \n\
+                    \x20                     \n";
+    run_stdout(&["--sunday", "-m", "9", "1752"], expected)?;
+    run_stdout(&["--sunday", "--reform", "1752", "-m", "9", "1752"], expected)?;
+
+    let gregorian = "   September 1752     \n\
+                     Su Mo Tu We Th Fr Sa  \n\
+                     \x20               1  2  \n\
+                     \x203  4  5  6  7  8  9  \n\
+                     10 11 12 13 14 15 16  \n\
+                     17 18 19 20 21 22 23  \n\
+                     24 25 26 27 28 29 30  \n\
+                     \x20                     \n";
+    run_stdout(&["--sunday", "--reform", "gregorian", "-m", "9", "1752"], gregorian)
+}
+
+// --------------------------------------------------
+#[test]
+fn reform_julian() -> Result<()> {
+    // 儒略历的 2000 年 1 月 1 日是格里高利历的 1 月 14 日, 星期五;
+    // 1900 年在儒略历中是闰年
+    let cmd = calr()?
+        .args(["--sunday", "--reform", "julian", "-m", "1", "2000"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    assert_eq!(stdout.lines().nth(2), Some("                1  2  "));
+    calr()?
+        .args(["--reform", "julian", "-m", "2", "1900"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("29"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn day_of_year() -> Result<()> {
+    let expected = "      September 1752         \n\
+                    \x20Su  Mo  Tu  We  Th  Fr  Sa  \n\
+                    \x20       245 246 258 259 260  \n\
+                    261 262 263 264 265 266 267  \n\
+                    268 269 270 271 272 273 274  \n\
+                    \x20                            \n\
+                    \x20                            \n\
+                    \x20                            \n";
+    run_stdout(&["--sunday", "-j", "-m", "9", "1752"], expected)?;
+
+    // 跳过的 11 天也计算在内, 1752 年是闰年
+    calr()?
+        .args(["--sunday", "-j", "-m", "12", "1752"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("366"));
+
+    // 全年时每行两个月, 年份居中
+    let cmd = calr()?
+        .args(["--sunday", "-j", "2020"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(cmd.get_output().stdout.clone())?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], format!("{:>28}", "2020"));
+    assert_eq!(lines[1].len(), 58);
+    assert_eq!(lines.len(), 1 + 6 * 8 + 5);
+    Ok(())
+}
+
+// --------------------------------------------------
+fn gen_events(name: &str, contents: &str) -> Result<std::path::PathBuf> {
+    let path = std::env::temp_dir()
+        .join(format!("calr-{}-{name}", std::process::id()));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+// --------------------------------------------------
+#[test]
+fn events_toml() -> Result<()> {
+    let path = gen_events(
+        "events.toml",
+        "[[event]]\n\
+         date = 2020-10-01\n\
+         summary = \"National Day holiday\"\n\
+         repeat = \"yearly\"\n\
+         \n\
+         [[event]]\n\
+         date = \"2021-10-15\"\n\
+         summary = \"Offsite\"\n",
+    )?;
+    let expected = "    October 2021      \n\
+                    Su Mo Tu We Th Fr Sa  \n\
+                    \x20              \x1b[1;31m 1\x1b[0m  2  \n\
+                    \x203  4  5  6  7  8  9  \n\
+                    10 11 12 13 14 \x1b[1;32m15\x1b[0m 16  \n\
+                    17 18 19 20 21 22 23  \n\
+                    24 25 26 27 28 29 30  \n\
+                    31                    \n\
+                    \x1b[31m 1 National Day holi\x1b[0m  \n\
+                    \x1b[32m15 Offsite          \x1b[0m  \n";
+    run_stdout(
+        &["--sunday", "--events", path.to_str().unwrap(), "-m", "10", "2021"],
+        expected,
+    )?;
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn events_wide_summary() -> Result<()> {
+    // 全角字符占两列, 按显示宽度截断, 不会撑宽月历
+    let path = gen_events(
+        "wide.toml",
+        "[[event]]\n\
+         date = 2021-10-01\n\
+         summary = \"国庆节假期七天连休\"\n",
+    )?;
+    let output = calr()?
+        .args(["--events", path.to_str().unwrap(), "-m", "10", "2021"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let legend: Vec<&str> = stdout.lines().skip(8).collect();
+    assert_eq!(legend, ["\x1b[31m 1 国庆节假期七天连 \x1b[0m  "]);
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn events_ics() -> Result<()> {
+    let path = gen_events(
+        "events.ics",
+        "BEGIN:VCALENDAR\r\n\
+         BEGIN:VEVENT\r\n\
+         DTSTART;VALUE=DATE:20260105\r\n\
+         SUMMARY:On-call\\, team A\r\n\
+         RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TU\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         DTSTART:20200525T000000Z\r\n\
+         SUMMARY:Memorial\r\n \x20Day\r\n\
+         RRULE:FREQ=YEARLY;BYMONTH=5;BYDAY=-1MO\r\n\
+         END:VEVENT\r\n\
+         BEGIN:VEVENT\r\n\
+         DTSTART:20260131\r\n\
+         SUMMARY:Month end\r\n\
+         RRULE:FREQ=MONTHLY;COUNT=3\r\n\
+         END:VEVENT\r\n\
+         END:VCALENDAR\r\n",
+    )?;
+    let file = path.to_str().unwrap();
+    let legend = |month: &str| -> Result<Vec<String>> {
+        let output = calr()?
+            .args(["--events", file, "-m", month, "2026"])
+            .output()?;
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout)?;
+        Ok(stdout.lines().skip(8).map(String::from).collect())
+    };
+
+    // 隔周的周一和周二; 五月最后一个周一; 每月 31 日共三次, 二月没有 31 日
+    assert_eq!(
+        legend("1")?,
+        [
+            "\x1b[31m5,6,19,20 On-call, t\x1b[0m  ",
+            "\x1b[33m31 Month end        \x1b[0m  ",
+        ]
+    );
+    assert_eq!(legend("2")?, ["\x1b[31m2,3,16,17 On-call, t\x1b[0m  "]);
+    assert_eq!(
+        legend("5")?,
+        [
+            "\x1b[31m11,12,25,26 On-call,\x1b[0m  ",
+            "\x1b[32m25 Memorial Day     \x1b[0m  ",
+            "\x1b[33m31 Month end        \x1b[0m  ",
+        ]
+    );
+    assert_eq!(legend("7")?.len(), 1);
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn dies_bad_events() -> Result<()> {
+    calr()?
+        .args(["--events", "holidays.txt"])
+        .assert()
+        .failure()
+        .stderr("calr: holidays.txt: events file must end in .ics or .toml\n");
+
+    let path = gen_events("bad.ics", "BEGIN:VEVENT\nDTSTART:20260101\nRRULE:FREQ=HOURLY\nEND:VEVENT\n")?;
+    calr()?
+        .args(["--events", path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(predicate::str::ends_with("unsupported RRULE frequency \"HOURLY\"\n"));
+    fs::remove_file(&path)?;
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_json() -> Result<()> {
+    let path = gen_events(
+        "format.toml",
+        "[[event]]\n\
+         date = 2020-02-14\n\
+         summary = \"Valentine's Day\"\n",
+    )?;
+    let output = calr()?
+        .args(["--sunday", "-m", "2", "2020", "--format", "json", "--events"])
+        .arg(&path)
+        .output()?;
+    fs::remove_file(&path)?;
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert!(json.get("year").is_none());
+    let month = &json["months"][0];
+    assert_eq!(month["month"], 2);
+    assert_eq!(month["weekdays"][0], "Su");
+    assert_eq!(month["weeks"].as_array().unwrap().len(), 5);
+    // 2020-02-01 是星期六
+    let first_week = &month["weeks"][0];
+    assert_eq!(first_week["iso_week"], 5);
+    assert!(first_week["days"][5].is_null());
+    assert_eq!(first_week["days"][6]["day"], 1);
+    assert_eq!(first_week["days"][6]["weekday"], "Sa");
+    assert_eq!(first_week["days"][6]["today"], false);
+    assert_eq!(month["weeks"][2]["days"][5]["events"][0], 0);
+    assert_eq!(month["events"][0]["summary"], "Valentine's Day");
+    assert_eq!(month["events"][0]["dates"][0], "2020-02-14");
+
+    let output = calr()?
+        .args(["2020", "--format", "json"])
+        .output()?;
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(json["year"], 2020);
+    assert_eq!(json["months"].as_array().unwrap().len(), 12);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_html() -> Result<()> {
+    let path = gen_events(
+        "format.ics",
+        "BEGIN:VCALENDAR\n\
+         BEGIN:VEVENT\n\
+         DTSTART;VALUE=DATE:20200214\n\
+         SUMMARY:Valentine's <Day>\n\
+         END:VEVENT\n\
+         END:VCALENDAR\n",
+    )?;
+    let output = calr()?
+        .args(["--sunday", "-m", "2", "2020", "--format", "html", "--events"])
+        .arg(&path)
+        .output()?;
+    fs::remove_file(&path)?;
+    let html = String::from_utf8(output.stdout)?;
+    assert!(html.starts_with("<table class=\"month\">\n<caption>February 2020"));
+    assert!(html.contains("<thead><tr><th>Su</th><th>Mo</th>"));
+    assert!(html.contains(
+        "<td class=\"event\" title=\"Valentine's &lt;Day&gt;\">14</td>"
+    ));
+    assert!(html.contains("<td>29</td></tr>"));
+    assert!(html.ends_with("</table>\n"));
+
+    // 今天所在的月份
+    let output = calr()?
+        .args(["--format", "html"])
+        .output()?;
+    let html = String::from_utf8(output.stdout)?;
+    assert_eq!(html.matches("class=\"today\"").count(), 1);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn format_ics() -> Result<()> {
+    let path = gen_events(
+        "format-ics.toml",
+        "[[event]]\n\
+         date = 2020-01-06\n\
+         summary = \"Stand-up, weekly\"\n\
+         repeat = \"weekly\"\n",
+    )?;
+    let output = calr()?
+        .args(["-m", "2", "2020", "--format", "ics", "--events"])
+        .arg(&path)
+        .output()?;
+    fs::remove_file(&path)?;
+    let ics = String::from_utf8(output.stdout)?;
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT\r\n").count(), 4);
+    assert!(ics.contains("DTSTART;VALUE=DATE:20200203\r\n"));
+    assert!(ics.contains("SUMMARY:Stand-up\\, weekly\r\n"));
+    Ok(())
+}
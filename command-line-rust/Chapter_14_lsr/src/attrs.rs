@@ -0,0 +1,118 @@
+// 扩展属性、ACL、SELinux 安全上下文和 statx 时间戳, 只支持 Linux
+//   -l 时权限后的标记: + 有 ACL, @ 有其他扩展属性
+//   --xattrs: 在每一行下面列出扩展属性的名称和大小
+//   -Z: 显示 security.selinux 中的安全上下文, 没有时为 ?
+//   --time: 用 statx 取创建、访问或状态改变时间
+// 其他平台上没有扩展属性, 安全上下文为 ?, 也没有创建时间
+use clap::ValueEnum;
+#[cfg(target_os = "linux")]
+use rustix::fs::{AtFlags, CWD, StatxFlags, lgetxattr, llistxattr, statx};
+use std::path::Path;
+
+const ACL_ATTRS: [&str; 2] =
+    ["system.posix_acl_access", "system.posix_acl_default"];
+const SELINUX_ATTR: &str = "security.selinux";
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TimeField {
+    #[value(name = "modification", alias("mtime"))]
+    Modified,
+    #[value(alias("atime"), alias("use"))]
+    Access,
+    #[value(alias("ctime"), alias("status"))]
+    Change,
+    #[value(alias("creation"))]
+    Birth,
+}
+
+// --------------------------------------------------
+// 文件的扩展属性名称及其值的大小; 文件系统不支持时为空
+#[cfg(target_os = "linux")]
+pub fn list(path: &Path) -> Vec<(String, usize)> {
+    let Ok(size) = llistxattr(path, &mut [0u8; 0][..]) else {
+        return vec![];
+    };
+    let mut names = vec![0; size];
+    let Ok(len) = llistxattr(path, &mut names[..]) else {
+        return vec![];
+    };
+    names[..len]
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let name = String::from_utf8_lossy(name).into_owned();
+            let size =
+                lgetxattr(path, name.as_str(), &mut [0u8; 0][..]).unwrap_or(0);
+            (name, size)
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+// 权限字符串后面的标记
+pub fn marker(attrs: &[(String, usize)]) -> &'static str {
+    if attrs
+        .iter()
+        .any(|(name, _)| ACL_ATTRS.contains(&name.as_str()))
+    {
+        "+"
+    } else if attrs.iter().any(|(name, _)| name != SELINUX_ATTR) {
+        "@"
+    } else {
+        ""
+    }
+}
+
+// --------------------------------------------------
+#[cfg(target_os = "linux")]
+pub fn security_context(path: &Path) -> String {
+    let mut value = vec![0; 256];
+    match lgetxattr(path, SELINUX_ATTR, &mut value[..]) {
+        Ok(len) => String::from_utf8_lossy(&value[..len])
+            .trim_end_matches('\0')
+            .to_string(),
+        Err(_) => "?".to_string(),
+    }
+}
+
+// --------------------------------------------------
+// 符号链接本身的时间戳(秒, 纳秒); 文件系统不记录创建时间时为 None
+#[cfg(target_os = "linux")]
+pub fn timestamp(path: &Path, field: TimeField) -> Option<(i64, u32)> {
+    let mask = match field {
+        TimeField::Modified => StatxFlags::MTIME,
+        TimeField::Access => StatxFlags::ATIME,
+        TimeField::Change => StatxFlags::CTIME,
+        TimeField::Birth => StatxFlags::BTIME,
+    };
+    let stx = statx(CWD, path, AtFlags::SYMLINK_NOFOLLOW, mask).ok()?;
+    // 内核在 stx_mask 中标明实际返回了哪些字段
+    if !StatxFlags::from_bits_retain(stx.stx_mask).contains(mask) {
+        return None;
+    }
+    let time = match field {
+        TimeField::Modified => stx.stx_mtime,
+        TimeField::Access => stx.stx_atime,
+        TimeField::Change => stx.stx_ctime,
+        TimeField::Birth => stx.stx_btime,
+    };
+    Some((time.tv_sec, time.tv_nsec))
+}
+
+// --------------------------------------------------
+#[cfg(not(target_os = "linux"))]
+pub fn list(_path: &Path) -> Vec<(String, usize)> {
+    vec![]
+}
+
+// --------------------------------------------------
+#[cfg(not(target_os = "linux"))]
+pub fn security_context(_path: &Path) -> String {
+    "?".to_string()
+}
+
+// --------------------------------------------------
+#[cfg(not(target_os = "linux"))]
+pub fn timestamp(_path: &Path, _field: TimeField) -> Option<(i64, u32)> {
+    None
+}
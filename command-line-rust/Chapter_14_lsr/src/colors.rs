@@ -0,0 +1,121 @@
+// --color: 按 LS_COLORS 为文件名着色, 格式与 dircolors 的输出相同:
+//   di=01;34:ln=01;36:*.tar=01;31
+// 两个字母的键表示文件类型, *后缀 表示扩展名
+use crate::Entry;
+use clap::ValueEnum;
+use std::{
+    collections::HashMap,
+    env,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+};
+
+// 未设置 LS_COLORS 时使用 GNU ls 的内置颜色
+const DEFAULT_COLORS: &str = "di=01;34:ln=01;36:pi=40;33:so=01;35:do=01;35:\
+    bd=40;33;01:cd=40;33;01:or=40;31;01:su=37;41:sg=30;43:tw=30;42:ow=34;42:\
+    st=37;44:ex=01;32";
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum When {
+    #[value(alias("yes"), alias("force"))]
+    Always,
+    #[value(alias("tty"), alias("if-tty"))]
+    Auto,
+    #[value(alias("no"), alias("none"))]
+    Never,
+}
+
+#[derive(Debug, Default)]
+pub struct Colors {
+    types: HashMap<String, String>,
+    extensions: Vec<(String, String)>, // 按出现的顺序, 后面的优先
+}
+
+impl Colors {
+    // --------------------------------------------------
+    pub fn from_env() -> Self {
+        match env::var("LS_COLORS") {
+            Ok(spec) if !spec.is_empty() => Self::parse(&spec),
+            _ => Self::parse(DEFAULT_COLORS),
+        }
+    }
+
+    // --------------------------------------------------
+    // 无法识别的项直接忽略, 与 ls 一样
+    pub fn parse(spec: &str) -> Self {
+        let mut colors = Self::default();
+        for (key, code) in
+            spec.split(':').filter_map(|item| item.split_once('='))
+        {
+            match key.strip_prefix('*') {
+                Some(suffix) => colors
+                    .extensions
+                    .push((suffix.to_lowercase(), code.to_string())),
+                None => {
+                    colors.types.insert(key.to_string(), code.to_string());
+                }
+            }
+        }
+        colors
+    }
+
+    // --------------------------------------------------
+    // 给 text 加上 entry 对应的颜色, 没有颜色时原样返回
+    pub fn paint(&self, text: &str, entry: &Entry) -> String {
+        match self
+            .code(entry)
+            .filter(|code| !code.is_empty() && *code != "0" && *code != "00")
+        {
+            Some(code) => format!("\x1b[{code}m{text}\x1b[0m"),
+            None => text.to_string(),
+        }
+    }
+
+    // --------------------------------------------------
+    fn code(&self, entry: &Entry) -> Option<&str> {
+        let metadata = &entry.metadata;
+        let file_type = metadata.file_type();
+        let mode = metadata.mode();
+        let get = |key: &str| self.types.get(key).map(String::as_str);
+
+        let key = if file_type.is_dir() {
+            let other_writable = mode & 0o002 != 0;
+            match (mode & 0o1000 != 0, other_writable) {
+                (true, true) => "tw",
+                (false, true) => "ow",
+                (true, false) => "st",
+                (false, false) => "di",
+            }
+        } else if file_type.is_symlink() {
+            // 指向不存在的目标的链接; 没有设置 or 时与其他链接相同
+            match entry.path.metadata() {
+                Err(_) if get("or").is_some_and(|c| !c.is_empty()) => "or",
+                _ => "ln",
+            }
+        } else if file_type.is_fifo() {
+            "pi"
+        } else if file_type.is_socket() {
+            "so"
+        } else if file_type.is_block_device() {
+            "bd"
+        } else if file_type.is_char_device() {
+            "cd"
+        } else if mode & 0o4000 != 0 && get("su").is_some() {
+            "su"
+        } else if mode & 0o2000 != 0 && get("sg").is_some() {
+            "sg"
+        } else if mode & 0o111 != 0 && get("ex").is_some() {
+            "ex"
+        } else {
+            // 普通文件按扩展名匹配, 不区分大小写
+            let name = entry.name.to_lowercase();
+            let by_extension = self
+                .extensions
+                .iter()
+                .rev()
+                .find(|(suffix, _)| name.ends_with(suffix.as_str()))
+                .map(|(_, code)| code.as_str());
+            return by_extension.or_else(|| get("fi"));
+        };
+        get(key)
+    }
+}
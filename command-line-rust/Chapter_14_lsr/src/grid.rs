@@ -0,0 +1,99 @@
+// -C/-x: 像 ls 一样把名称排成适合终端宽度的多列
+// 各列宽度取该列中最长的名称, 列之间至少两个空格; 按显示宽度计算, 不计颜色转义
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    Down,    // -C: 先竖着排
+    Across,  // -x: 先横着排
+    OneLine, // -1: 每行一个
+}
+
+// 一个格子: 要输出的文本(可能带颜色)及其显示宽度
+#[derive(Debug)]
+pub struct Cell {
+    pub text: String,
+    pub width: usize,
+}
+
+const GAP: usize = 2;
+
+// --------------------------------------------------
+pub fn render(cells: &[Cell], layout: Layout, line_width: usize) -> String {
+    if cells.is_empty() {
+        return String::new();
+    }
+    let (rows, widths) = match layout {
+        Layout::OneLine => (cells.len(), vec![0]),
+        _ => fit(cells, layout, line_width),
+    };
+    let cols = widths.len();
+
+    let mut out = String::new();
+    for row in 0..rows {
+        let line: Vec<(usize, &Cell)> = (0..cols)
+            .filter_map(|col| {
+                let idx = index(layout, row, col, rows, cols);
+                cells.get(idx).map(|cell| (col, cell))
+            })
+            .collect();
+        for (pos, (col, cell)) in line.iter().enumerate() {
+            out.push_str(&cell.text);
+            // 每行最后一个名称后面不补空格
+            if pos + 1 < line.len() {
+                out.push_str(&" ".repeat(widths[*col] - cell.width + GAP));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// --------------------------------------------------
+// 第 row 行第 col 列的格子的下标
+fn index(
+    layout: Layout,
+    row: usize,
+    col: usize,
+    rows: usize,
+    cols: usize,
+) -> usize {
+    match layout {
+        Layout::Across => row * cols + col,
+        _ => col * rows + row,
+    }
+}
+
+// --------------------------------------------------
+// 找出能放进 line_width 的最多列数, 返回行数和各列宽度
+fn fit(
+    cells: &[Cell],
+    layout: Layout,
+    line_width: usize,
+) -> (usize, Vec<usize>) {
+    let count = cells.len();
+    // 每列至少一个字符加上列间距, 列数不会超过这个值
+    let max_cols = ((line_width + GAP) / (1 + GAP)).clamp(1, count);
+    for cols in (1..=max_cols).rev() {
+        let rows = count.div_ceil(cols);
+        // 竖排时最后几列可能是空的, 按实际用到的列数计算
+        let cols = match layout {
+            Layout::Across => cols,
+            _ => count.div_ceil(rows),
+        };
+        let widths: Vec<usize> = (0..cols)
+            .map(|col| {
+                (0..rows)
+                    .filter_map(|row| {
+                        cells.get(index(layout, row, col, rows, cols))
+                    })
+                    .map(|cell| cell.width)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let total = widths.iter().sum::<usize>() + GAP * (cols - 1);
+        if total <= line_width || cols == 1 {
+            return (rows, widths);
+        }
+    }
+    (count, vec![0])
+}
@@ -1,11 +1,28 @@
+mod attrs;
+mod colors;
+mod grid;
 mod owner;  // 声明 owner 模块(对应 owner.rs 文件)
+mod sort;
+mod time_style;
 
 use anyhow::Result;
+use attrs::TimeField;
 use chrono::{DateTime, Local};  // 日期时间处理
-use clap::Parser;
+use clap::{ArgAction, Parser};
+use colors::{Colors, When};
+use grid::{Cell, Layout};
 use owner::Owner;   // 自定义的文件权限所有者枚举
-use std::{fs, os::unix::fs::MetadataExt, path::PathBuf}; // 文件系统和路径操作
+use serde::Serialize;
+use sort::{SortBy, SortOptions};
+use std::{
+    env, fs,
+    io::{self, IsTerminal, Write},
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::{Path, PathBuf},
+}; // 文件系统和路径操作
+use time_style::TimeStyle;
 use tabular::{Row, Table};  // 表格格式化输出
+use unicode_width::UnicodeWidthStr; // 名称的显示宽度
 use users::{get_group_by_gid, get_user_by_uid};     // 用户和组信息查询
 
 // cargo add  clap --features derive
@@ -14,7 +31,7 @@ use users::{get_group_by_gid, get_user_by_uid};     // 用户和组信息查询
 // cargo add --dev assert_cmd predicates pretty_assertions rand sys_info
 
 #[derive(Debug, Parser)]  // 自动实现 Parser trait,用于命令行解析 into()
-#[command(author, version, about)]
+#[command(author, version, about, disable_help_flag = true)] // -h 用于 --human-readable
 /// Rust version of `ls`
 struct Args {
     /// Files and/or directories
@@ -28,113 +45,675 @@ struct Args {
     /// Show all files
     #[arg(short('a'), long("all"))]
     show_hidden: bool,
+
+    /// Print sizes like 1.5K, 234M in long listing
+    #[arg(short('h'), long)]
+    human_readable: bool,
+
+    /// Time format: full-iso, long-iso, iso, locale or +FORMAT
+    #[arg(long, value_name = "STYLE", default_value = "default")]
+    time_style: TimeStyle,
+
+    /// Print the inode number of each file
+    #[arg(short, long)]
+    inode: bool,
+
+    /// Like -l, but list numeric user and group IDs
+    #[arg(short('n'), long)]
+    numeric_uid_gid: bool,
+
+    /// Sort by modification time, newest first
+    #[arg(short('t'), overrides_with_all(["size", "extension", "natural"]))]
+    time: bool,
+
+    /// Sort by file size, largest first
+    #[arg(short('S'), overrides_with_all(["time", "extension", "natural"]))]
+    size: bool,
+
+    /// Sort alphabetically by extension
+    #[arg(short('X'), overrides_with_all(["time", "size", "natural"]))]
+    extension: bool,
+
+    /// Natural sort of (version) numbers within names
+    #[arg(short('v'), overrides_with_all(["time", "size", "extension"]))]
+    natural: bool,
+
+    /// Reverse the sort order
+    #[arg(short, long)]
+    reverse: bool,
+
+    /// List subdirectories recursively
+    #[arg(short('R'), long)]
+    recursive: bool,
+
+    /// List directories themselves, not their contents
+    #[arg(short, long)]
+    directory: bool,
+
+    /// List directories before files
+    #[arg(long)]
+    group_directories_first: bool,
+
+    /// List entries by columns (default on a terminal)
+    #[arg(short('C'), overrides_with_all(["across", "one_per_line"]))]
+    columns: bool,
+
+    /// List entries by lines instead of by columns
+    #[arg(short('x'), overrides_with_all(["columns", "one_per_line"]))]
+    across: bool,
+
+    /// List one entry per line (default when not on a terminal)
+    #[arg(short('1'), overrides_with_all(["columns", "across"]))]
+    one_per_line: bool,
+
+    /// Colorize names using LS_COLORS: always, auto or never
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_value = "never",
+        default_missing_value = "always"
+    )]
+    color: When,
+
+    /// Which time to show and sort by
+    #[arg(
+        long("time"),
+        value_name = "WORD",
+        value_enum,
+        default_value = "modification"
+    )]
+    time_field: TimeField,
+
+    /// List extended attribute names and sizes in long listing
+    #[arg(long)]
+    xattrs: bool,
+
+    /// Print the security context of each file
+    #[arg(short('Z'), long)]
+    context: bool,
+
+    /// Print entry metadata as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
+}
+
+// 长格式的显示选项
+#[derive(Debug)]
+struct LongFormat {
+    human: bool,
+    time_style: TimeStyle,
+    inode: bool,
+    numeric: bool,
+    colors: Option<Colors>,
+    time: TimeField,
+    xattrs: bool,
+    context: bool,
+}
+
+// 一个要列出的条目及其元数据
+#[derive(Debug)]
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: String, // 显示和排序用的名称: 参数原样显示, 目录中的条目只显示文件名
+    pub metadata: fs::Metadata,
+}
+
+impl Entry {
+    // --------------------------------------------------
+    pub fn is_dir(&self) -> bool {
+        self.metadata.is_dir()
+    }
+
+    // --------------------------------------------------
+    // --time 选择的时间(秒, 纳秒); 没有记录创建时间时为 None
+    pub fn time(&self, field: TimeField) -> Option<(i64, u32)> {
+        let metadata = &self.metadata;
+        match field {
+            TimeField::Modified => {
+                Some((metadata.mtime(), metadata.mtime_nsec() as u32))
+            }
+            TimeField::Access => {
+                Some((metadata.atime(), metadata.atime_nsec() as u32))
+            }
+            TimeField::Change => {
+                Some((metadata.ctime(), metadata.ctime_nsec() as u32))
+            }
+            TimeField::Birth => attrs::timestamp(&self.path, field),
+        }
+    }
+}
+
+// 一组一起排序和显示的条目: 作为参数的文件(dir 为 None), 或一个目录的内容
+#[derive(Debug)]
+struct Listing {
+    dir: Option<PathBuf>,
+    entries: Vec<Entry>,
 }
 
 // --------------------------------------------------
 fn main() {
-    if let Err(e) = run(Args::parse()) {
-        eprintln!("{e}");
-        std::process::exit(1);
-    }
+    clr_core::exit(run(Args::parse()));
 }
 
 // --------------------------------------------------
 fn run(args: Args) -> Result<()> {
     // 根据参数查找文件
-    let paths = find_files(&args.paths, args.show_hidden)?;
-    // 长格式输出
-    if args.long {
-        println!("{}", format_output(&paths)?);
-    } else { // 简单输出，只显示路径
-        for path in paths {
-            println!("{}", path.display());
+    let by = if args.time {
+        SortBy::Time
+    } else if args.size {
+        SortBy::Size
+    } else if args.extension {
+        SortBy::Extension
+    } else if args.natural {
+        SortBy::Version
+    } else {
+        SortBy::Name
+    };
+    let sort = SortOptions {
+        by,
+        reverse: args.reverse,
+        directories_first: args.group_directories_first,
+        time: args.time_field,
+        collation: sort::locale_collation(),
+    };
+    let long = args.long || args.numeric_uid_gid;
+    // 与 ls 一样, 长格式和 -d 时不跟随作为参数的符号链接; JSON 与长格式相同
+    let follow_links = !long && !args.json && !args.directory;
+    let listings = find_files(
+        &args.paths,
+        args.show_hidden,
+        args.recursive,
+        args.directory,
+        follow_links,
+        sort,
+    )?;
+    let mut out = clr_core::stdout();
+    if args.json {
+        writeln!(out, "{}", format_json(&listings, args.time_field)?)?;
+        return Ok(out.flush()?);
+    }
+    let long_format = LongFormat {
+        human: args.human_readable,
+        time_style: args.time_style,
+        inode: args.inode,
+        numeric: args.numeric_uid_gid,
+        colors: match args.color {
+            When::Always => Some(Colors::from_env()),
+            When::Auto if io::stdout().is_terminal() => {
+                Some(Colors::from_env())
+            }
+            _ => None,
+        },
+        time: args.time_field,
+        xattrs: args.xattrs,
+        context: args.context,
+    };
+    // 终端上默认分列显示, 否则每行一个
+    let layout = if args.one_per_line {
+        Layout::OneLine
+    } else if args.across {
+        Layout::Across
+    } else if args.columns || io::stdout().is_terminal() {
+        Layout::Down
+    } else {
+        Layout::OneLine
+    };
+    let line_width = line_width();
+
+    // 多个参数或 -R 时在每个目录的内容前显示 "目录:"
+    let headers = args.paths.len() > 1 || args.recursive;
+
+    for (idx, listing) in listings.iter().enumerate() {
+        if idx > 0 {
+            writeln!(out)?; // 各组之间空一行
+        }
+        if let Some(dir) = &listing.dir
+            && headers
+        {
+            writeln!(out, "{}:", dir.display())?;
+        }
+        // 长格式输出
+        if long {
+            if listing.dir.is_some() {
+                writeln!(out, "total {}", total_blocks(&listing.entries))?;
+            }
+            write!(out, "{}", format_output(&listing.entries, &long_format)?)?;
+        } else { // 简单输出，只显示名称
+            let cells = short_cells(&listing.entries, &long_format);
+            write!(out, "{}", grid::render(&cells, layout, line_width))?;
         }
     }
+    out.flush()?;
     Ok(())
 }
 
 // --------------------------------------------------
-fn find_files(paths: &[String], show_hidden: bool) -> Result<Vec<PathBuf>> {
-    let mut results = vec![];
+// 与 ls 一样, 先列出作为参数的文件, 再依次列出各目录的内容, 每组分别排序
+fn find_files(
+    paths: &[String],
+    show_hidden: bool,
+    recursive: bool,
+    directory: bool,
+    follow_links: bool,
+    sort: SortOptions,
+) -> Result<Vec<Listing>> {
+    let mut files = vec![];
+    let mut dirs = vec![];
     for name in paths {
-        match fs::metadata(name) { // 获取文件元数据
-            Err(e) => eprintln!("{name}: {e}"),
-            Ok(meta) => {
-                if meta.is_dir() { // 如果是目录
-                    for entry in fs::read_dir(name)? { // read_dir 读取目录内容
-                        let entry = entry?;
-                        let path = entry.path();
-                        // 检查是否为隐藏文件（以 . 开头）
-                        let is_hidden =
-                            path.file_name().map_or(false, |file_name| {
-                                file_name.to_string_lossy().starts_with('.')
-                            });
-                        // 如果不是隐藏文件，或者设置了显示隐藏文件，则添加到结果中
-                        if !is_hidden || show_hidden {
-                            results.push(entry.path());
-                        }
-                    }
+        // 获取文件元数据; 跟随符号链接时, 失效的链接仍按链接本身列出
+        let metadata = match follow_links {
+            true => fs::metadata(name).or_else(|_| fs::symlink_metadata(name)),
+            false => fs::symlink_metadata(name),
+        };
+        match metadata {
+            Err(e) => clr_core::warn(format!("{name}: {e}")),
+            Ok(metadata) => {
+                let entry = Entry {
+                    path: PathBuf::from(name),
+                    name: name.to_string(),
+                    metadata,
+                };
+                // -d 时目录本身作为条目列出
+                if entry.is_dir() && !directory { // 如果是目录
+                    dirs.push(entry);
                 } else { // 普通文件
-                    results.push(PathBuf::from(name));
+                    files.push(entry);
                 }
             }
         }
     }
+    sort::sort_entries(&mut files, sort);
+    sort::sort_entries(&mut dirs, sort);
+
+    let mut results = vec![];
+    if !files.is_empty() {
+        results.push(Listing { dir: None, entries: files });
+    }
+    for dir in dirs {
+        list_dir(dir.path, show_hidden, recursive, sort, &mut results);
+    }
 
     Ok(results)
 }
 
 // --------------------------------------------------
-fn format_output(paths: &[PathBuf]) -> Result<String> {
-    // 定义表格格式：{:<} 左对齐，{:>} 右对齐
-    // 对应：文件类型、权限、链接数、用户、组、大小、修改时间、文件名
-    //         1   2     3     4     5     6     7     8
-    let fmt = "{:<}{:<}  {:>}  {:<}  {:<}  {:>}  {:<}  {:<}";
-    let mut table = Table::new(fmt);
+// 列出一个目录的内容; -R 时接着按相同的顺序列出其中的子目录(符号链接不是目录, 不会进入)
+fn list_dir(
+    dir: PathBuf,
+    show_hidden: bool,
+    recursive: bool,
+    sort: SortOptions,
+    results: &mut Vec<Listing>,
+) {
+    // 无法读取的目录只报告错误, 继续处理其他目录
+    let entries = match fs::read_dir(&dir) { // read_dir 读取目录内容
+        Ok(entries) => entries,
+        Err(e) => {
+            clr_core::warn(format!("{}: {e}", dir.display()));
+            return;
+        }
+    };
+    let mut contents = vec![];
+    for entry in entries {
+        // 单个条目出错时只报告这一项, 其余条目照常列出
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                clr_core::warn(format!("{}: {e}", dir.display()));
+                continue;
+            }
+        };
+        // 检查是否为隐藏文件（以 . 开头）
+        let is_hidden = path.file_name().is_some_and(|file_name| {
+            file_name.to_string_lossy().starts_with('.')
+        });
+        // 如果不是隐藏文件，或者设置了显示隐藏文件，则添加到结果中
+        if !is_hidden || show_hidden {
+            // 不跟随符号链接, 显示链接本身
+            match path.symlink_metadata() {
+                Ok(metadata) => contents.push(Entry {
+                    metadata,
+                    name: entry_name(&path),
+                    path,
+                }),
+                Err(e) => clr_core::warn(format!("{}: {e}", path.display())),
+            }
+        }
+    }
+    sort::sort_entries(&mut contents, sort);
+
+    let subdirs: Vec<PathBuf> = match recursive {
+        true => contents
+            .iter()
+            .filter(|entry| entry.is_dir())
+            .map(|entry| entry.path.clone())
+            .collect(),
+        false => vec![],
+    };
+    results.push(Listing { dir: Some(dir), entries: contents });
+    for subdir in subdirs {
+        list_dir(subdir, show_hidden, recursive, sort, results);
+    }
+}
 
-    for path in paths {
-        let metadata = path.metadata()?; // 获取文件元数据
+// --------------------------------------------------
+// 目录中的条目只显示文件名
+fn entry_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+// --------------------------------------------------
+// 简单输出中每个条目的文本, 前面是 -i 时对齐的 inode 号和 -Z 时的安全上下文
+fn short_cells(entries: &[Entry], opts: &LongFormat) -> Vec<Cell> {
+    let inode_width = match opts.inode {
+        true => entries
+            .iter()
+            .map(|entry| entry.metadata.ino().to_string().len())
+            .max()
+            .unwrap_or(0),
+        false => 0,
+    };
+    entries
+        .iter()
+        .map(|entry| {
+            let mut width = entry.name.width();
+            let mut text = match &opts.colors {
+                Some(colors) => colors.paint(&entry.name, entry),
+                None => entry.name.clone(),
+            };
+            if opts.context {
+                let context = attrs::security_context(&entry.path);
+                width += context.width() + 1;
+                text = format!("{context} {text}");
+            }
+            if opts.inode {
+                text = format!("{:>inode_width$} {text}", entry.metadata.ino());
+                width += inode_width + 1;
+            }
+            Cell { text, width }
+        })
+        .collect()
+}
+
+// --------------------------------------------------
+// 输出的宽度: COLUMNS 环境变量, 其次是终端的宽度, 默认 80
+fn line_width() -> usize {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|cols| cols.parse().ok())
+        .filter(|&cols| cols > 0)
+        .or_else(|| {
+            terminal_size::terminal_size().map(|(width, _)| width.0 as usize)
+        })
+        .unwrap_or(80)
+}
+
+// --------------------------------------------------
+// 目录中各条目占用的块数, 以 1K 为单位, 与 ls -l 的 total 一致
+fn total_blocks(entries: &[Entry]) -> u64 {
+    // st_blocks 以 512 字节为单位
+    entries.iter().map(|entry| entry.metadata.blocks().div_ceil(2)).sum()
+}
+
+// --------------------------------------------------
+// 长格式和 --json 共用的条目信息
+#[derive(Debug, Serialize)]
+struct Details {
+    path: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    inode: u64,
+    mode: Mode,
+    nlink: u64,
+    uid: u32,
+    user: Option<String>, // 找不到用户时为 null
+    gid: u32,
+    group: Option<String>,
+    size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device: Option<Device>, // 只有设备文件才有
+    mtime: String, // RFC 3339
+    target: Option<String>, // 符号链接的目标
+    #[serde(skip)]
+    time: Option<DateTime<Local>>, // --time 选择的时间
+    #[serde(skip)]
+    attrs: Vec<(String, usize)>, // 扩展属性
+}
+
+#[derive(Debug, Serialize)]
+struct Mode {
+    octal: String,    // 如 0755, 包括特殊位
+    symbolic: String, // 如 drwxr-xr-x
+}
+
+#[derive(Debug, Serialize)]
+struct Device {
+    major: u64,
+    minor: u64,
+}
+
+impl Details {
+    // --------------------------------------------------
+    fn new(entry: &Entry, time: TimeField) -> Result<Self> {
+        let Entry { path, metadata, .. } = entry;
 
         // 获取用户信息
         let uid = metadata.uid(); // 用户ID
         let user = get_user_by_uid(uid) // 获取用户名
-            .map(|u| u.name().to_string_lossy().into_owned())
-            .unwrap_or_else(|| uid.to_string()); // 如果找不到用户，显示UID
+            .map(|u| u.name().to_string_lossy().into_owned());
 
         // 获取组信息
         let gid = metadata.gid(); // 组ID
         let group = get_group_by_gid(gid)
-            .map(|g| g.name().to_string_lossy().into_owned())  // 获取组名 // into_owned 通过clone 获得所有权
-            .unwrap_or_else(|| gid.to_string()); // 如果找不到组，显示GID
-        
-        // 确定文件类型：目录或普通文件
-        let file_type = if path.is_dir() { "d" } else { "-" };
-        // 格式化权限字符串
-        let perms = format_mode(metadata.mode());
+            .map(|g| g.name().to_string_lossy().into_owned());  // 获取组名 // into_owned 通过clone 获得所有权
+
+        // 确定文件类型
+        let type_char = file_type(metadata);
+        let kind = match type_char {
+            'd' => "directory",
+            'l' => "symlink",
+            'c' => "char_device",
+            'b' => "block_device",
+            'p' => "fifo",
+            's' => "socket",
+            _ => "file",
+        };
+        let device = matches!(type_char, 'c' | 'b').then(|| {
+            let (major, minor) = device_numbers(metadata.rdev());
+            Device { major, minor }
+        });
         // 转换修改时间
         let modified: DateTime<Local> = DateTime::from(metadata.modified()?);
+        let target = fs::read_link(path)
+            .ok()
+            .filter(|_| type_char == 'l')
+            .map(|target| target.display().to_string());
+
+        Ok(Self {
+            path: path.display().to_string(),
+            kind,
+            inode: metadata.ino(),
+            mode: Mode {
+                octal: format!("{:04o}", metadata.mode() & 0o7777),
+                // 格式化权限字符串
+                symbolic: format!(
+                    "{type_char}{}",
+                    format_mode(metadata.mode())
+                ),
+            },
+            nlink: metadata.nlink(),
+            uid,
+            user,
+            gid,
+            group,
+            size: metadata.len(),
+            device,
+            mtime: modified.to_rfc3339(),
+            target,
+            // 没有记录创建时间时为 None
+            time: entry
+                .time(time)
+                .and_then(|(secs, nsecs)| DateTime::from_timestamp(secs, nsecs))
+                .map(|time| time.with_timezone(&Local)),
+            attrs: attrs::list(path),
+        })
+    }
+}
+
+// --------------------------------------------------
+fn format_output(entries: &[Entry], long: &LongFormat) -> Result<String> {
+    // 定义表格格式：{:<} 左对齐，{:>} 右对齐
+    // 对应：(-i 时的 inode)、类型和权限、ACL/扩展属性标记、链接数、用户、组、
+    //       (-Z 时的安全上下文)、大小、时间、文件名
+    let mut fmt = String::new();
+    if long.inode {
+        fmt.push_str("{:>}  ");
+    }
+    fmt.push_str("{:<}{:<}  {:>}  {:<}  {:<}  ");
+    if long.context {
+        fmt.push_str("{:<}  ");
+    }
+    fmt.push_str("{:>}  {:<}  {:<}");
+    let mut table = Table::new(&fmt);
+    let now = Local::now();
+
+    for entry in entries {
+        let details = Details::new(entry, long.time)?;
+
+        // 找不到用户或组时以及 -n 时显示 UID 和 GID
+        let user = match &details.user {
+            Some(name) if !long.numeric => name.clone(),
+            _ => details.uid.to_string(),
+        };
+        let group = match &details.group {
+            Some(name) if !long.numeric => name.clone(),
+            _ => details.gid.to_string(),
+        };
+        // 设备文件显示主、次设备号, 其他文件显示大小
+        let size = match &details.device {
+            Some(Device { major, minor }) => format!("{major}, {minor}"),
+            None if long.human => human_size(details.size),
+            None => details.size.to_string(),
+        };
+        // --time 选择的时间, 没有时显示 -
+        let time = details
+            .time
+            .map_or("-".to_string(), |time| long.time_style.format(time, now));
+        // 目录中的条目只显示文件名, 完整路径只在 --json 中;
+        // 符号链接显示 "名称 -> 目标"
+        let mut name = entry.name.clone();
+        if let Some(colors) = &long.colors {
+            name = colors.paint(&name, entry);
+        }
+        if let Some(target) = &details.target {
+            name = format!("{name} -> {target}");
+        }
 
         // 添加行到表格
+        let mut row = Row::new();
+        if long.inode {
+            row.add_cell(details.inode); // inode 号
+        }
+        row.add_cell(&details.mode.symbolic) // 文件类型和权限
+            .add_cell(attrs::marker(&details.attrs)) // + 或 @
+            .add_cell(details.nlink) // 硬链接数
+            .add_cell(user) // 用户名
+            .add_cell(group); // 组名
+        if long.context {
+            row.add_cell(attrs::security_context(&entry.path)); // 安全上下文
+        }
         table.add_row(
-            Row::new()
-                .with_cell(file_type) // 1. 文件类型
-                .with_cell(perms) // 2. 权限
-                .with_cell(metadata.nlink()) // 3. 硬链接数
-                .with_cell(user) // 4. 用户名
-                .with_cell(group) // 5. 组名
-                .with_cell(metadata.len()) // 6. 文件大小(字节)
-                .with_cell(modified.format("%b %d %y %H:%M")) // 7. 修改时间
-                .with_cell(path.display()), // 8. 文件名
+            row.with_cell(size) // 文件大小(字节)
+                .with_cell(time) // 时间
+                .with_cell(name), // 文件名
         );
+        // --xattrs: 每个扩展属性一行
+        if long.xattrs {
+            for (attr, size) in &details.attrs {
+                table.add_heading(format!("\t{attr}\t{size:>4}"));
+            }
+        }
     }
 
     Ok(format!("{table}"))
 }
 
+// --------------------------------------------------
+// --json: 所有条目的信息, 与长格式使用同样的数据
+fn format_json(listings: &[Listing], time: TimeField) -> Result<String> {
+    let details = listings
+        .iter()
+        .flat_map(|listing| &listing.entries)
+        .map(|entry| Details::new(entry, time))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(serde_json::to_string_pretty(&details)?)
+}
+
+// --------------------------------------------------
+// ls -l 第一列的文件类型
+fn file_type(metadata: &fs::Metadata) -> char {
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else {
+        '-'
+    }
+}
+
+// --------------------------------------------------
+// 从 st_rdev 中取出主、次设备号, 与 glibc 的 major()/minor() 相同
+fn device_numbers(rdev: u64) -> (u64, u64) {
+    let major = ((rdev >> 8) & 0xfff) | ((rdev >> 32) & !0xfff);
+    let minor = (rdev & 0xff) | ((rdev >> 12) & !0xff);
+    (major, minor)
+}
+
+// --------------------------------------------------
+// -h: 以 1024 为进位, 向上取整, 小于 10 时保留一位小数, 如 4.0K、23M
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["K", "M", "G", "T", "P", "E"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    let tenths = (size * 10.0).ceil() / 10.0;
+    if tenths < 10.0 {
+        format!("{tenths:.1}{}", UNITS[unit])
+    } else {
+        format!("{}{}", size.ceil(), UNITS[unit])
+    }
+}
+
 // --------------------------------------------------
 /// Given a file mode in octal format like 0o751,
 /// return a string like "rwxr-x--x"
 // 将八进制的文件模式(如 0o751)转换为字符串(如 "rwxr-x--x")
+// setuid、setgid 和 sticky 位显示在对应的执行位上: s/S、s/S、t/T
 fn format_mode(mode: u32) -> String {
     format!(
         "{}{}{}",
@@ -150,11 +729,21 @@ fn format_mode(mode: u32) -> String {
 // 根据所有者和文件模式生成权限三元组(如 "r-x")
 fn mk_triple(mode: u32, owner: Owner) -> String {
     let [read, write, execute] = owner.masks();
+    // 有特殊位时小写表示同时可执行, 大写表示不可执行
+    let (set, unset) = match owner {
+        Owner::Other => ("t", "T"),
+        _ => ("s", "S"),
+    };
     format!(
         "{}{}{}",
         if mode & read == 0 { "-" } else { "r" },
         if mode & write == 0 { "-" } else { "w" },
-        if mode & execute == 0 { "-" } else { "x" },
+        match (mode & execute != 0, mode & owner.special() != 0) {
+            (false, false) => "-",
+            (true, false) => "x",
+            (true, true) => set,
+            (false, true) => unset,
+        },
     )
 }
 
@@ -13,6 +13,15 @@ impl Owner {
             Self::Other => [0o004, 0o002, 0o001],
         }
     }
+
+    // 显示在执行位上的特殊位: setuid、setgid、sticky
+    pub fn special(self) -> u32 {
+        match self {
+            Self::User => 0o4000,
+            Self::Group => 0o2000,
+            Self::Other => 0o1000,
+        }
+    }
 }
 /*
 0o004 (八进制 004) = 二进制 000000100 - 其他用户读权限
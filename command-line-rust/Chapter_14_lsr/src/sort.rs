@@ -0,0 +1,159 @@
+// 条目排序: 默认按名称, -t 修改时间, -S 大小, -X 扩展名, -v 名称中的数字按数值比较
+use crate::{Entry, attrs::TimeField};
+use std::{cmp::Ordering, env, os::unix::fs::MetadataExt};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    Name,
+    Time,      // 最新的在前, 按 --time 选择的时间
+    Size,      // 最大的在前
+    Extension, // 按扩展名, 没有扩展名的在前
+    Version,   // 自然排序, file2 在 file10 之前
+}
+
+// 名称的比较方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collation {
+    Bytes,  // C/POSIX 区域: 按字节
+    Locale, // 其他区域: 先忽略大小写和标点, 相同时再按字节
+}
+
+// Entry::time 的结果
+type Timestamp = Option<(i64, u32)>;
+
+#[derive(Debug, Clone, Copy)]
+pub struct SortOptions {
+    pub by: SortBy,
+    pub reverse: bool,
+    pub directories_first: bool,
+    pub time: TimeField,
+    pub collation: Collation,
+}
+
+// --------------------------------------------------
+// -t 时每个条目只取一次时间: --time=birth 每次都要 statx
+pub fn sort_entries(entries: &mut Vec<Entry>, opts: SortOptions) {
+    let mut keyed: Vec<_> = entries
+        .drain(..)
+        .map(|entry| match opts.by {
+            SortBy::Time => (entry.time(opts.time), entry),
+            _ => (None, entry),
+        })
+        .collect();
+    keyed.sort_by(|(time_a, a), (time_b, b)| {
+        let order = compare(a, b, (*time_a, *time_b), opts);
+        if opts.reverse { order.reverse() } else { order }
+    });
+    entries.extend(keyed.into_iter().map(|(_, entry)| entry));
+    // 目录始终在前, 不受 -r 影响
+    if opts.directories_first {
+        entries.sort_by_key(|entry| !entry.is_dir());
+    }
+}
+
+// --------------------------------------------------
+// times 是 -t 时两个条目的时间
+fn compare(
+    a: &Entry,
+    b: &Entry,
+    times: (Timestamp, Timestamp),
+    opts: SortOptions,
+) -> Ordering {
+    let (name_a, name_b) = (a.name.as_str(), b.name.as_str());
+    let by_name = || match opts.by {
+        SortBy::Version => compare_version(name_a, name_b),
+        _ => compare_names(name_a, name_b, opts.collation),
+    };
+    match opts.by {
+        SortBy::Name | SortBy::Version => by_name(),
+        SortBy::Time => {
+            let (time_a, time_b) = times;
+            time_b.cmp(&time_a).then_with(by_name)
+        }
+        SortBy::Size => {
+            b.metadata.size().cmp(&a.metadata.size()).then_with(by_name)
+        }
+        SortBy::Extension => compare_names(
+            extension(name_a),
+            extension(name_b),
+            opts.collation,
+        )
+        .then_with(by_name),
+    }
+}
+
+// --------------------------------------------------
+// 最后一个点之后的部分; 以点开头的隐藏文件名不算扩展名
+fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => &name[idx + 1..],
+        _ => "",
+    }
+}
+
+// --------------------------------------------------
+pub fn compare_names(a: &str, b: &str, collation: Collation) -> Ordering {
+    match collation {
+        Collation::Bytes => a.cmp(b),
+        Collation::Locale => {
+            let key = |name: &str| -> Vec<char> {
+                name.chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .flat_map(char::to_lowercase)
+                    .collect()
+            };
+            key(a).cmp(&key(b)).then_with(|| a.cmp(b))
+        }
+    }
+}
+
+// --------------------------------------------------
+// 把名称分成数字段和非数字段, 数字段按数值比较, 其余按字节
+pub fn compare_version(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    while !a_rest.is_empty() && !b_rest.is_empty() {
+        let (a_part, a_tail) = split_run(a_rest);
+        let (b_part, b_tail) = split_run(b_rest);
+        let is_num = |s: &str| s.starts_with(|c: char| c.is_ascii_digit());
+        let order = if is_num(a_part) && is_num(b_part) {
+            // 去掉前导零后, 位数多的数值大
+            let (a_num, b_num) = (
+                a_part.trim_start_matches('0'),
+                b_part.trim_start_matches('0'),
+            );
+            a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num))
+        } else {
+            a_part.cmp(b_part)
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+        (a_rest, b_rest) = (a_tail, b_tail);
+    }
+    a_rest.len().cmp(&b_rest.len()).then_with(|| a.cmp(b))
+}
+
+// --------------------------------------------------
+// 开头的一段连续数字或连续非数字
+fn split_run(s: &str) -> (&str, &str) {
+    let digit = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digit)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+// --------------------------------------------------
+// 与 strcoll 一样由 LC_ALL、LC_COLLATE、LANG 决定
+pub fn locale_collation() -> Collation {
+    let locale = ["LC_ALL", "LC_COLLATE", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|val| !val.is_empty())
+        .unwrap_or_default();
+    match locale.as_str() {
+        "" | "C" | "POSIX" => Collation::Bytes,
+        locale if locale.starts_with("C.") => Collation::Bytes,
+        _ => Collation::Locale,
+    }
+}
@@ -0,0 +1,62 @@
+// --time-style: 长格式中修改时间的显示方式
+use chrono::{
+    DateTime, Duration, Local,
+    format::{Item, StrftimeItems},
+};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeStyle {
+    Default,        // 原来的格式: Oct 19 26 09:17
+    FullIso,        // 2026-10-19 09:17:44.123456789 +0800
+    LongIso,        // 2026-10-19 09:17
+    Iso,            // 半年内 10-19 09:17, 否则 2026-10-19
+    Locale,         // GNU ls 的默认格式: Oct 19 09:17 或 Oct 19  2025
+    Format(String), // +FORMAT, strftime 格式
+}
+
+impl FromStr for TimeStyle {
+    type Err = String;
+
+    fn from_str(style: &str) -> Result<Self, Self::Err> {
+        match style {
+            "default" => Ok(Self::Default),
+            "full-iso" => Ok(Self::FullIso),
+            "long-iso" => Ok(Self::LongIso),
+            "iso" => Ok(Self::Iso),
+            "locale" => Ok(Self::Locale),
+            _ => match style.strip_prefix('+') {
+                // 检查格式中是否有无法识别的 % 说明符
+                Some(fmt)
+                    if !StrftimeItems::new(fmt).any(|i| i == Item::Error) =>
+                {
+                    Ok(Self::Format(fmt.to_string()))
+                }
+                _ => Err(format!(r#"invalid time style "{style}""#)),
+            },
+        }
+    }
+}
+
+impl TimeStyle {
+    // --------------------------------------------------
+    pub fn format(
+        &self,
+        time: DateTime<Local>,
+        now: DateTime<Local>,
+    ) -> String {
+        // 与 ls 一样, 半年内且不在将来的时间算作最近
+        let recent = time <= now && now - time < Duration::days(365 / 2);
+        let fmt = match self {
+            Self::Default => "%b %d %y %H:%M",
+            Self::FullIso => "%Y-%m-%d %H:%M:%S.%f %z",
+            Self::LongIso => "%Y-%m-%d %H:%M",
+            Self::Iso if recent => "%m-%d %H:%M",
+            Self::Iso => "%Y-%m-%d",
+            Self::Locale if recent => "%b %e %H:%M",
+            Self::Locale => "%b %e  %Y",
+            Self::Format(fmt) => fmt,
+        };
+        time.format(fmt).to_string()
+    }
+}
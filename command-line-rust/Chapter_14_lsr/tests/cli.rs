@@ -2,8 +2,9 @@ use anyhow::Result;
 use assert_cmd::Command;
 use predicates::prelude::*;
 use pretty_assertions::assert_eq;
-use rand::{distributions::Alphanumeric, Rng};
+use rand::{distr::Alphanumeric, Rng};
 use std::fs;
+use tempfile::TempDir;
 
 const PRG: &str = "lsr";
 const HIDDEN: &str = "tests/inputs/.hidden";
@@ -14,7 +15,7 @@ const FOX: &str = "tests/inputs/fox.txt";
 // --------------------------------------------------
 fn gen_bad_file() -> String {
     loop {
-        let filename: String = rand::thread_rng()
+        let filename: String = rand::rng()
             .sample_iter(&Alphanumeric)
             .take(7)
             .map(char::from)
@@ -32,11 +33,16 @@ fn bad_file() -> Result<()> {
     let bad = gen_bad_file();
     let expected =
         format!("{}: No such file or directory (os error 2)", &bad);
+    // 跳过出错的参数, 列出其他的, 最后以 1 退出
+    let tmp = gen_dir("bad-file", &[("fox.txt", 1, 0)])?;
+    let dir = tmp.path();
+    let fox = dir.join("fox.txt").display().to_string();
     Command::cargo_bin(PRG)?
-        .arg(&bad)
+        .args([&bad, &fox])
         .assert()
-        .success()
-        .stderr(predicate::str::contains(expected));
+        .code(1)
+        .stdout(format!("{fox}\n"))
+        .stderr(format!("lsr: {expected}\n"));
     Ok(())
 }
 
@@ -137,10 +143,10 @@ fn dir1() -> Result<()> {
     dir_short(
         &["tests/inputs"],
         &[
-            "tests/inputs/empty.txt",
-            "tests/inputs/bustle.txt",
-            "tests/inputs/fox.txt",
-            "tests/inputs/dir",
+            "empty.txt",
+            "bustle.txt",
+            "fox.txt",
+            "dir",
         ],
     )
 }
@@ -150,25 +156,25 @@ fn dir1_all() -> Result<()> {
     dir_short(
         &["tests/inputs", "--all"],
         &[
-            "tests/inputs/empty.txt",
-            "tests/inputs/bustle.txt",
-            "tests/inputs/fox.txt",
-            "tests/inputs/.hidden",
-            "tests/inputs/dir",
+            "empty.txt",
+            "bustle.txt",
+            "fox.txt",
+            ".hidden",
+            "dir",
         ],
     )
 }
 
 #[test]
 fn dir2() -> Result<()> {
-    dir_short(&["tests/inputs/dir"], &["tests/inputs/dir/spiders.txt"])
+    dir_short(&["tests/inputs/dir"], &["spiders.txt"])
 }
 
 #[test]
 fn dir2_all() -> Result<()> {
     dir_short(
         &["-a", "tests/inputs/dir"],
-        &["tests/inputs/dir/spiders.txt", "tests/inputs/dir/.gitkeep"],
+        &["spiders.txt", ".gitkeep"],
     )
 }
 
@@ -206,10 +212,10 @@ fn dir1_long() -> Result<()> {
     dir_long(
         &["-l", "tests/inputs"],
         &[
-            ("tests/inputs/empty.txt", "-rw-r--r--", "0"),
-            ("tests/inputs/bustle.txt", "-rw-r--r--", "193"),
-            ("tests/inputs/fox.txt", "-rw-------", "45"),
-            ("tests/inputs/dir", "drwxr-xr-x", ""),
+            ("empty.txt", "-rw-r--r--", "0"),
+            ("bustle.txt", "-rw-r--r--", "193"),
+            ("fox.txt", "-rw-------", "45"),
+            ("dir", "drwxr-xr-x", ""),
         ],
     )
 }
@@ -219,11 +225,11 @@ fn dir1_long_all() -> Result<()> {
     dir_long(
         &["-la", "tests/inputs"],
         &[
-            ("tests/inputs/empty.txt", "-rw-r--r--", "0"),
-            ("tests/inputs/bustle.txt", "-rw-r--r--", "193"),
-            ("tests/inputs/fox.txt", "-rw-------", "45"),
-            ("tests/inputs/dir", "drwxr-xr-x", ""),
-            ("tests/inputs/.hidden", "-rw-r--r--", "0"),
+            ("empty.txt", "-rw-r--r--", "0"),
+            ("bustle.txt", "-rw-r--r--", "193"),
+            ("fox.txt", "-rw-------", "45"),
+            ("dir", "drwxr-xr-x", ""),
+            (".hidden", "-rw-r--r--", "0"),
         ],
     )
 }
@@ -232,7 +238,7 @@ fn dir1_long_all() -> Result<()> {
 fn dir2_long() -> Result<()> {
     dir_long(
         &["--long", "tests/inputs/dir"],
-        &[("tests/inputs/dir/spiders.txt", "-rw-r--r--", "45")],
+        &[("spiders.txt", "-rw-r--r--", "45")],
     )
 }
 
@@ -241,8 +247,667 @@ fn dir2_long_all() -> Result<()> {
     dir_long(
         &["tests/inputs/dir", "--long", "--all"],
         &[
-            ("tests/inputs/dir/spiders.txt", "-rw-r--r--", "45"),
-            ("tests/inputs/dir/.gitkeep", "-rw-r--r--", "0"),
+            ("spiders.txt", "-rw-r--r--", "45"),
+            (".gitkeep", "-rw-r--r--", "0"),
         ],
     )
 }
+
+// --------------------------------------------------
+// 在临时目录中创建文件: (名称, 大小, 距今的秒数), 名称以 / 结尾的为目录;
+// 返回的 TempDir 被丢弃时删除整个目录, 断言失败时也不会留下
+fn gen_dir(name: &str, files: &[(&str, usize, u64)]) -> Result<TempDir> {
+    let tmp = tempfile::Builder::new()
+        .prefix(&format!("lsr-{name}-"))
+        .tempdir()?;
+    let dir = tmp.path();
+    let now = std::time::SystemTime::now();
+    for (file, size, age) in files {
+        let path = dir.join(file);
+        let time = now - std::time::Duration::from_secs(*age);
+        if file.ends_with('/') {
+            fs::create_dir(&path)?;
+            fs::File::open(&path)?.set_modified(time)?;
+        } else {
+            fs::write(&path, "x".repeat(*size))?;
+            fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(time)?;
+        }
+    }
+    Ok(tmp)
+}
+
+// --------------------------------------------------
+// 简单输出中的文件名, 按输出的顺序
+fn sorted_names(
+    dir: &std::path::Path,
+    args: &[&str],
+    lang: &str,
+) -> Result<Vec<String>> {
+    let output = Command::cargo_bin(PRG)?
+        .env_remove("LC_ALL")
+        .env_remove("LC_COLLATE")
+        .env("LANG", lang)
+        .args(args)
+        .arg(dir)
+        .output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter(|line| !line.starts_with("total "))
+        .filter_map(|line| line.split_whitespace().last())
+        .map(|path| path.rsplit('/').next().unwrap().to_string())
+        .collect())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_by_name() -> Result<()> {
+    let tmp = gen_dir(
+        "sort-name",
+        &[
+            ("b.txt", 1, 0),
+            ("A.txt", 1, 0),
+            ("file10", 1, 0),
+            ("file2", 1, 0),
+            ("_c", 1, 0),
+        ],
+    )?;
+    let dir = tmp.path();
+    // C 区域按字节
+    assert_eq!(
+        sorted_names(dir, &[], "C")?,
+        ["A.txt", "_c", "b.txt", "file10", "file2"]
+    );
+    // 其他区域忽略大小写和标点
+    assert_eq!(
+        sorted_names(dir, &[], "en_US.UTF-8")?,
+        ["A.txt", "b.txt", "_c", "file10", "file2"]
+    );
+    // -v 按数值比较名称中的数字
+    assert_eq!(
+        sorted_names(dir, &["-v"], "C")?,
+        ["A.txt", "_c", "b.txt", "file2", "file10"]
+    );
+    // 长格式使用同样的顺序
+    assert_eq!(
+        sorted_names(dir, &["-lrv"], "C")?,
+        ["file10", "file2", "b.txt", "_c", "A.txt"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn sort_by_time_size_extension() -> Result<()> {
+    let tmp = gen_dir(
+        "sort-keys",
+        &[
+            ("old.rs", 30, 3000),
+            ("new.txt", 10, 10),
+            ("mid.c", 20, 500),
+            ("README", 5, 100),
+            ("sub/", 0, 200),
+        ],
+    )?;
+    let dir = tmp.path();
+    assert_eq!(
+        sorted_names(dir, &["-t"], "C")?,
+        ["new.txt", "README", "sub", "mid.c", "old.rs"]
+    );
+    assert_eq!(
+        sorted_names(dir, &["-tr"], "C")?,
+        ["old.rs", "mid.c", "sub", "README", "new.txt"]
+    );
+    assert_eq!(
+        sorted_names(dir, &["-S", "--group-directories-first"], "C")?,
+        ["sub", "old.rs", "mid.c", "new.txt", "README"]
+    );
+    assert_eq!(
+        sorted_names(dir, &["-X"], "C")?,
+        ["README", "sub", "mid.c", "old.rs", "new.txt"]
+    );
+    // 后给出的排序方式优先
+    assert_eq!(
+        sorted_names(dir, &["-S", "-t", "-l"], "C")?,
+        ["new.txt", "README", "sub", "mid.c", "old.rs"]
+    );
+    // 目录在前, 不受 -r 影响
+    assert_eq!(
+        sorted_names(dir, &["-r", "--group-directories-first"], "C")?,
+        ["sub", "old.rs", "new.txt", "mid.c", "README"]
+    );
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn recursive() -> Result<()> {
+    let tmp = gen_dir(
+        "recursive",
+        &[
+            ("a/", 0, 0),
+            ("a/b/", 0, 0),
+            ("a/b/deep.txt", 3, 0),
+            ("a/file.txt", 3, 0),
+            (".hidden/", 0, 0),
+            ("top.txt", 3, 0),
+        ],
+    )?;
+    let dir = tmp.path();
+    let root = dir.display();
+    let expected = format!(
+        "{root}:\na\ntop.txt\n\n\
+         {root}/a:\nb\nfile.txt\n\n\
+         {root}/a/b:\ndeep.txt\n"
+    );
+    Command::cargo_bin(PRG)?
+        .env("LC_ALL", "C")
+        .arg("-R")
+        .arg(dir)
+        .assert()
+        .success()
+        .stdout(expected);
+
+    // -a 时也进入隐藏目录; 长格式中每个目录有 total 行
+    let output = Command::cargo_bin(PRG)?
+        .env("LC_ALL", "C")
+        .arg("-lRa")
+        .arg(dir)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let headers: Vec<&str> =
+        stdout.lines().filter(|line| line.ends_with(':')).collect();
+    assert_eq!(
+        headers,
+        [
+            format!("{root}:"),
+            format!("{root}/.hidden:"),
+            format!("{root}/a:"),
+            format!("{root}/a/b:"),
+        ]
+    );
+    let totals = stdout
+        .lines()
+        .filter(|line| line.starts_with("total "))
+        .count();
+    assert_eq!(totals, 4);
+    assert!(stdout.contains(&format!(":\ntotal 0\n\n{root}/a:")));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn directory_as_entry() -> Result<()> {
+    let tmp = gen_dir("directory", &[("sub/", 0, 0), ("sub/inner.txt", 3, 0)])?;
+    let dir = tmp.path();
+    let sub = dir.join("sub");
+    Command::cargo_bin(PRG)?
+        .arg("-d")
+        .arg(&sub)
+        .assert()
+        .success()
+        .stdout(format!("{}\n", sub.display()));
+
+    let output = Command::cargo_bin(PRG)?.arg("-ld").arg(&sub).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert_eq!(stdout.lines().count(), 1);
+    assert!(stdout.starts_with("drwx"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn unreadable_entries() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // 目录可读但不可进入时, 每个条目的 lstat 失败, 逐个报告
+    let tmp = gen_dir(
+        "unreadable",
+        &[("sub/", 0, 0), ("sub/a", 1, 0), ("sub/b", 1, 0)],
+    )?;
+    let dir = tmp.path();
+    let sub = dir.join("sub");
+    fs::set_permissions(&sub, fs::Permissions::from_mode(0o644))?;
+    // root 不受权限限制, 无法构造出错的条目
+    let enforced = fs::symlink_metadata(sub.join("a")).is_err();
+    let output = Command::cargo_bin(PRG)?.arg(&sub).output()?;
+    fs::set_permissions(&sub, fs::Permissions::from_mode(0o755))?;
+
+    if enforced {
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr)?;
+        let mut lines: Vec<&str> = stderr.lines().collect();
+        lines.sort();
+        let denied = |name| {
+            format!("lsr: {}/{name}: Permission denied (os error 13)", sub.display())
+        };
+        assert_eq!(lines, [denied("a"), denied("b")]);
+    }
+    Ok(())
+}
+
+// --------------------------------------------------
+// 长格式中某个文件的一行
+fn long_line(args: &[&str], path: &std::path::Path) -> Result<String> {
+    let output = Command::cargo_bin(PRG)?.args(args).arg(path).output()?;
+    assert!(output.status.success());
+    Ok(String::from_utf8(output.stdout)?.trim_end().to_string())
+}
+
+// --------------------------------------------------
+#[test]
+fn long_special_bits_and_links() -> Result<()> {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let tmp = gen_dir(
+        "special",
+        &[
+            ("suid", 0, 0),
+            ("sgid", 0, 0),
+            ("sticky/", 0, 0),
+            ("T/", 0, 0),
+        ],
+    )?;
+    let dir = tmp.path();
+    for (name, mode) in [
+        ("suid", 0o4755),
+        ("sgid", 0o2644),
+        ("sticky", 0o1777),
+        ("T", 0o1770),
+    ] {
+        fs::set_permissions(dir.join(name), fs::Permissions::from_mode(mode))?;
+    }
+    symlink("suid", dir.join("link"))?;
+    symlink("nowhere", dir.join("broken"))?;
+
+    let perms = |name: &str| -> Result<String> {
+        let line = long_line(&["-l"], &dir.join(name))?;
+        Ok(line.split_whitespace().next().unwrap().to_string())
+    };
+    assert_eq!(perms("suid")?, "-rwsr-xr-x");
+    assert_eq!(perms("sgid")?, "-rw-r-Sr--");
+    assert_eq!(perms("link")?, "lrwxrwxrwx");
+
+    // 目录的内容中, 符号链接显示链接本身和目标
+    let output = Command::cargo_bin(PRG)?.arg("-l").arg(dir).output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains(" link -> suid\n"));
+    assert!(stdout.contains(" broken -> nowhere\n"));
+    assert!(stdout.contains("drwxrwxrwt"));
+    assert!(stdout.contains("drwxrwx--T"));
+
+    // 简单输出中, 失效的链接也能列出
+    let broken = dir.join("broken");
+    Command::cargo_bin(PRG)?
+        .arg(&broken)
+        .assert()
+        .success()
+        .stdout(format!("{}\n", broken.display()));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn long_sizes_times_and_ids() -> Result<()> {
+    let tmp = gen_dir("long-opts", &[("big", 5000, 0), ("small", 100, 0)])?;
+    let dir = tmp.path();
+    let big = dir.join("big");
+    let old = dir.join("old");
+    fs::write(&old, "")?;
+    let time =
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_583_280_000);
+    fs::File::options()
+        .write(true)
+        .open(&old)?
+        .set_modified(time)?;
+
+    let parts =
+        |args: &[&str], path: &std::path::Path| -> Result<Vec<String>> {
+            Ok(long_line(args, path)?
+                .split_whitespace()
+                .map(str::to_string)
+                .collect())
+        };
+    assert_eq!(parts(&["-lh"], &big)?[4], "4.9K");
+    assert_eq!(parts(&["-lh"], &dir.join("small"))?[4], "100");
+    assert_eq!(parts(&["-l", "--human-readable"], &big)?[4], "4.9K");
+
+    // -n 显示数字的 UID 和 GID, 并且隐含 -l
+    let meta = fs::metadata(&big)?;
+    use std::os::unix::fs::MetadataExt;
+    let numeric = parts(&["-n"], &big)?;
+    assert_eq!(numeric[2], meta.uid().to_string());
+    assert_eq!(numeric[3], meta.gid().to_string());
+
+    // -i 在最前面显示 inode 号
+    assert_eq!(parts(&["-li"], &big)?[0], meta.ino().to_string());
+    Command::cargo_bin(PRG)?
+        .arg("-i")
+        .arg(&big)
+        .assert()
+        .success()
+        .stdout(format!("{} {}\n", meta.ino(), big.display()));
+
+    // --time-style, 时间按 UTC 显示
+    let styled = |style: &str| -> Result<String> {
+        let output = Command::cargo_bin(PRG)?
+            .env("TZ", "UTC")
+            .args(["-l", "--time-style", style])
+            .arg(&old)
+            .output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        let parts: Vec<&str> = stdout.split_whitespace().collect();
+        Ok(parts[5..parts.len() - 1].join(" "))
+    };
+    assert_eq!(styled("long-iso")?, "2020-03-04 00:00");
+    assert_eq!(styled("full-iso")?, "2020-03-04 00:00:00.000000000 +0000");
+    assert_eq!(styled("iso")?, "2020-03-04");
+    assert_eq!(styled("locale")?, "Mar 4 2020");
+    assert_eq!(styled("+%s")?, "1583280000");
+
+    Command::cargo_bin(PRG)?
+        .args(["-l", "--time-style", "bad"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(r#"invalid time style "bad""#));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn grid_columns() -> Result<()> {
+    let names = [
+        "alpha",
+        "beta",
+        "gamma",
+        "delta",
+        "epsilon",
+        "zeta",
+        "eta",
+        "日本語.txt",
+    ];
+    let files: Vec<(&str, usize, u64)> =
+        names.iter().map(|name| (*name, 0, 0)).collect();
+    let tmp = gen_dir("grid", &files)?;
+    let dir = tmp.path();
+    let run = |args: &[&str], columns: &str| -> Result<String> {
+        let output = Command::cargo_bin(PRG)?
+            .env("LC_ALL", "C")
+            .env("COLUMNS", columns)
+            .args(args)
+            .arg(dir)
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+
+    // 竖着排, 宽字符占两列
+    assert_eq!(
+        run(&["-C"], "30")?,
+        "alpha  epsilon  zeta\n\
+         beta   eta      日本語.txt\n\
+         delta  gamma\n"
+    );
+    // 横着排
+    assert_eq!(
+        run(&["-x"], "30")?,
+        "alpha    beta        delta\n\
+         epsilon  eta         gamma\n\
+         zeta     日本語.txt\n"
+    );
+    // 足够宽时排成一行
+    assert_eq!(
+        run(&["-C"], "200")?,
+        "alpha  beta  delta  epsilon  eta  gamma  zeta  日本語.txt\n"
+    );
+    // -1 以及不是终端时每行一个
+    assert_eq!(run(&["-C", "-1"], "200")?, run(&[], "200")?);
+    assert_eq!(run(&[], "200")?.lines().count(), names.len());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn grid_many_files() -> Result<()> {
+    // 列数从宽度允许的最多列开始找, 文件很多时也很快
+    let names: Vec<String> = (0..5000).map(|n| format!("f{n}")).collect();
+    let files: Vec<(&str, usize, u64)> =
+        names.iter().map(|name| (name.as_str(), 0, 0)).collect();
+    let tmp = gen_dir("grid-many", &files)?;
+    let dir = tmp.path();
+    let output = Command::cargo_bin(PRG)?
+        .env("LC_ALL", "C")
+        .env("COLUMNS", "80")
+        .arg("-C")
+        .arg(dir)
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.lines().all(|line| line.len() <= 80));
+    assert_eq!(stdout.split_whitespace().count(), names.len());
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn color() -> Result<()> {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let tmp = gen_dir(
+        "color",
+        &[
+            ("sub/", 0, 0),
+            ("run.sh", 0, 0),
+            ("pkg.TAR", 0, 0),
+            ("plain", 0, 0),
+        ],
+    )?;
+    let dir = tmp.path();
+    fs::set_permissions(dir.join("run.sh"), fs::Permissions::from_mode(0o755))?;
+    symlink("nowhere", dir.join("orphan"))?;
+
+    let run = |args: &[&str], ls_colors: &str| -> Result<String> {
+        let output = Command::cargo_bin(PRG)?
+            .env("LC_ALL", "C")
+            .env("LS_COLORS", ls_colors)
+            .args(args)
+            .arg(dir)
+            .output()?;
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    assert_eq!(
+        run(&["--color"], "di=01;34:ex=01;32:or=40;31;01:*.tar=01;31")?,
+        "\x1b[40;31;01morphan\x1b[0m\n\
+         \x1b[01;31mpkg.TAR\x1b[0m\n\
+         plain\n\
+         \x1b[01;32mrun.sh\x1b[0m\n\
+         \x1b[01;34msub\x1b[0m\n"
+    );
+    // 未设置 LS_COLORS 时使用默认颜色
+    assert!(run(&["--color=always"], "")?.contains("\x1b[01;34msub\x1b[0m"));
+    // 不是终端时 auto 不着色
+    assert!(!run(&["--color=auto"], "")?.contains('\x1b'));
+    assert!(!run(&[], "di=01;34")?.contains('\x1b'));
+    // 长格式中只给名称着色
+    let long = run(&["-l", "--color=yes"], "ln=01;36:or=")?;
+    assert!(long.contains(" \x1b[01;36morphan\x1b[0m -> nowhere\n"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+#[cfg(target_os = "linux")] // 扩展属性只在 Linux 上支持
+fn xattr_and_acl_markers() -> Result<()> {
+    use rustix::fs::{XattrFlags, lsetxattr};
+
+    let tmp = gen_dir(
+        "xattrs",
+        &[("acl", 1, 0), ("plain", 1, 0), ("tagged", 1, 0)],
+    )?;
+    let dir = tmp.path();
+    lsetxattr(
+        dir.join("tagged"),
+        "user.comment",
+        b"hello",
+        XattrFlags::empty(),
+    )?;
+    lsetxattr(dir.join("tagged"), "user.empty", b"", XattrFlags::empty())?;
+    // 最小的访问 ACL: user::rw- user:0:rw- group::r-- mask::rw- other::r--
+    let acl: Vec<u8> = [2u32, 0x6_0001, u32::MAX, 0x6_0002, 0, 0x4_0004]
+        .into_iter()
+        .chain([u32::MAX, 0x6_0010, u32::MAX, 0x4_0020, u32::MAX])
+        .flat_map(u32::to_le_bytes)
+        .collect();
+    lsetxattr(
+        dir.join("acl"),
+        "system.posix_acl_access",
+        &acl,
+        XattrFlags::empty(),
+    )?;
+
+    let output = Command::cargo_bin(PRG)?
+        .env("LC_ALL", "C")
+        .arg("-l")
+        .arg(dir)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let perms: Vec<&str> = stdout
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    assert_eq!(perms, ["-rw-rw-r--+", "-rw-r--r--", "-rw-r--r--@"]);
+
+    let output = Command::cargo_bin(PRG)?
+        .arg("-l")
+        .arg("--xattrs")
+        .arg(dir.join("tagged"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.contains(&"\tuser.comment\t   5"));
+    assert!(lines.contains(&"\tuser.empty\t   0"));
+
+    // -Z 在组后面增加一列安全上下文, 短格式中在名称前面
+    let plain = dir.join("plain");
+    let columns = |args: &[&str]| -> Result<usize> {
+        let output =
+            Command::cargo_bin(PRG)?.args(args).arg(&plain).output()?;
+        Ok(String::from_utf8(output.stdout)?.split_whitespace().count())
+    };
+    assert_eq!(columns(&["-lZ"])?, columns(&["-l"])? + 1);
+    assert_eq!(columns(&["-Z"])?, 2);
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn time_fields() -> Result<()> {
+    let tmp = gen_dir("times", &[("a", 1, 0), ("b", 1, 0)])?;
+    let dir = tmp.path();
+    let epoch = std::time::UNIX_EPOCH;
+    let days = |n: u64| epoch + std::time::Duration::from_secs(n * 86_400);
+    // a 修改得晚, b 访问得晚
+    let times = [
+        ("a", days(18_000), days(19_000)),
+        ("b", days(18_500), days(18_900)),
+    ];
+    for (name, accessed, modified) in times {
+        let times = fs::FileTimes::new()
+            .set_accessed(accessed)
+            .set_modified(modified);
+        fs::File::options()
+            .write(true)
+            .open(dir.join(name))?
+            .set_times(times)?;
+    }
+
+    let names = |args: &[&str]| -> Result<String> {
+        let output = Command::cargo_bin(PRG)?.args(args).arg(dir).output()?;
+        Ok(String::from_utf8(output.stdout)?.replace('\n', " "))
+    };
+    assert_eq!(names(&["-t"])?, "a b ");
+    assert_eq!(names(&["-t", "--time=access"])?, "b a ");
+    assert_eq!(names(&["-t", "--time", "atime"])?, "b a ");
+
+    let time = |args: &[&str]| -> Result<String> {
+        let output = Command::cargo_bin(PRG)?
+            .env("TZ", "UTC")
+            .args(["-l", "--time-style=long-iso"])
+            .args(args)
+            .arg(dir.join("b"))
+            .output()?;
+        let stdout = String::from_utf8(output.stdout)?;
+        let parts: Vec<&str> = stdout.split_whitespace().collect();
+        Ok(parts[5..parts.len() - 1].join(" "))
+    };
+    assert_eq!(time(&[])?, "2021-09-30 00:00");
+    assert_eq!(time(&["--time=access"])?, "2020-08-26 00:00");
+    // 状态改变时间和创建时间都是刚才
+    let year = chrono::Utc::now().format("%Y-").to_string();
+    assert!(time(&["--time=change"])?.starts_with(&year));
+    let birth = time(&["--time=birth"])?;
+    assert!(birth.starts_with(&year) || birth == "-");
+
+    Command::cargo_bin(PRG)?
+        .args(["--time", "never"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value 'never'"));
+    Ok(())
+}
+
+// --------------------------------------------------
+#[test]
+fn json() -> Result<()> {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let tmp = gen_dir("json", &[("file", 3, 0), ("sub/", 0, 0)])?;
+    let dir = tmp.path();
+    symlink("file", dir.join("link"))?;
+    fs::set_permissions(dir.join("file"), fs::Permissions::from_mode(0o4640))?;
+    let modified =
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000_000);
+    fs::File::options()
+        .write(true)
+        .open(dir.join("file"))?
+        .set_modified(modified)?;
+
+    let output = Command::cargo_bin(PRG)?
+        .env("TZ", "UTC")
+        .arg("--json")
+        .arg(dir)
+        .output()?;
+    assert!(output.status.success());
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    let entries = value.as_array().unwrap();
+    assert_eq!(entries.len(), 3);
+
+    let file = &entries[0];
+    assert_eq!(file["path"], dir.join("file").display().to_string());
+    assert_eq!(file["type"], "file");
+    assert_eq!(file["mode"]["octal"], "4640");
+    assert_eq!(file["mode"]["symbolic"], "-rwSr-----");
+    assert_eq!(file["nlink"], 1);
+    assert_eq!(file["size"], 3);
+    assert_eq!(file["mtime"], "2001-09-09T01:46:40+00:00");
+    assert_eq!(file["target"], serde_json::Value::Null);
+    assert!(file["uid"].is_u64() && file["gid"].is_u64());
+
+    let link = &entries[1];
+    assert_eq!(link["type"], "symlink");
+    assert_eq!(link["mode"]["symbolic"], "lrwxrwxrwx");
+    assert_eq!(link["target"], "file");
+
+    let sub = &entries[2];
+    assert_eq!(sub["type"], "directory");
+    assert!(sub["mode"]["symbolic"].as_str().unwrap().starts_with('d'));
+
+    // 与长格式的数据相同
+    let long = long_line(&["-l"], &dir.join("file"))?;
+    assert!(long.starts_with("-rwSr-----"));
+    Ok(())
+}
@@ -0,0 +1,93 @@
+// 压缩输入的解压: 先按魔数识别压缩格式, 读到的字节不够识别时再看扩展名
+use anyhow::Result;
+use bzip2::bufread::MultiBzDecoder;
+use flate2::bufread::MultiGzDecoder;
+use std::io::{BufRead, BufReader};
+use xz2::bufread::XzDecoder;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Gzip,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+// --------------------------------------------------
+pub fn detect(header: &[u8], filename: &str) -> Option<Format> {
+    let magic = [
+        (&b"\x1f\x8b"[..], Format::Gzip),
+        (b"BZh", Format::Bzip2),
+        (b"\xfd7zXZ\x00", Format::Xz),
+        (b"\x28\xb5\x2f\xfd", Format::Zstd),
+    ];
+    if let Some(&(_, format)) =
+        magic.iter().find(|(bytes, _)| header.starts_with(bytes))
+    {
+        return Some(format);
+    }
+
+    // 读到的字节太少(如管道)时, 才退回到扩展名判断;
+    // 够长却不匹配魔数的是普通文本, 空文件也没有要解压的内容
+    let longest = magic.iter().map(|(bytes, _)| bytes.len()).max()?;
+    if header.is_empty() || header.len() >= longest {
+        return None;
+    }
+    let ext = filename.rsplit_once('.').map(|(_, ext)| ext)?;
+    match ext {
+        "gz" | "tgz" => Some(Format::Gzip),
+        "bz2" | "tbz2" => Some(Format::Bzip2),
+        "xz" | "txz" => Some(Format::Xz),
+        "zst" | "tzst" => Some(Format::Zstd),
+        _ => None,
+    }
+}
+
+// --------------------------------------------------
+// 非压缩文件原样返回
+pub fn decompress(
+    mut reader: Box<dyn BufRead>,
+    filename: &str,
+) -> Result<Box<dyn BufRead>> {
+    let format = detect(reader.fill_buf()?, filename);
+    Ok(match format {
+        None => reader,
+        Some(Format::Gzip) => {
+            Box::new(BufReader::new(MultiGzDecoder::new(reader)))
+        }
+        Some(Format::Bzip2) => {
+            Box::new(BufReader::new(MultiBzDecoder::new(reader)))
+        }
+        Some(Format::Xz) => {
+            Box::new(BufReader::new(XzDecoder::new_multi_decoder(reader)))
+        }
+        Some(Format::Zstd) => Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(reader)?,
+        )),
+    })
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{Format, detect};
+
+    #[test]
+    fn test_detect() {
+        // 按魔数识别, 与扩展名无关
+        assert_eq!(
+            detect(b"\x1f\x8b\x08\x00\x00\x00", "a.txt"),
+            Some(Format::Gzip)
+        );
+        assert_eq!(
+            detect(b"\x28\xb5\x2f\xfd\x00\x00", "a"),
+            Some(Format::Zstd)
+        );
+        // 扩展名是 .gz 的普通文本原样读取
+        assert_eq!(detect(b"plain text\n", "plain.gz"), None);
+        assert_eq!(detect(b"", "empty.gz"), None);
+        // 只读到几个字节时才看扩展名
+        assert_eq!(detect(b"\x1f", "short.gz"), Some(Format::Gzip));
+        assert_eq!(detect(b"\x1f", "short.txt"), None);
+    }
+}
@@ -0,0 +1,61 @@
+// 错误信息和退出码, 所有工具保持一致:
+//   错误信息写到标准错误, 格式为 "prog: 信息", 如 "catr: foo.txt: No such file..."
+//   0 成功; 写入关闭的管道时也是 0, 与 ripgrep 一样不输出错误
+//   1 有错误, 包括跳过了无法读取的文件
+//   2 参数错误, 由 clap 处理
+use crate::output::is_broken_pipe;
+use std::{
+    env,
+    fmt::Display,
+    path::Path,
+    process,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+static FAILED: AtomicBool = AtomicBool::new(false); // 是否调用过 warn
+
+// --------------------------------------------------
+// 程序名, 取自 argv[0] 的文件名
+fn prog() -> &'static str {
+    static PROG: OnceLock<String> = OnceLock::new();
+    PROG.get_or_init(|| {
+        env::args_os()
+            .next()
+            .as_deref()
+            .and_then(|arg| Path::new(arg).file_stem())
+            .map_or("clr".to_string(), |name| {
+                name.to_string_lossy().into_owned()
+            })
+    })
+}
+
+// --------------------------------------------------
+// 输出提示(如 tailr 发现文件被替换), 格式与 warn 相同, 不影响退出码
+pub fn notice(msg: impl Display) {
+    eprintln!("{}: {msg}", prog());
+}
+
+// --------------------------------------------------
+// 报告错误并继续, 最后的退出码为 1
+pub fn warn(err: impl Display) {
+    eprintln!("{}: {err}", prog());
+    FAILED.store(true, Ordering::Relaxed);
+}
+
+// --------------------------------------------------
+// 在 main 中调用, 按 run 的结果和之前的 warn 退出
+pub fn exit(result: anyhow::Result<()>) -> ! {
+    let failed = FAILED.load(Ordering::Relaxed);
+    let code = match result {
+        Ok(()) => i32::from(failed),
+        Err(e) if is_broken_pipe(&e) => i32::from(failed),
+        Err(e) => {
+            eprintln!("{}: {e}", prog());
+            1
+        }
+    };
+    process::exit(code)
+}
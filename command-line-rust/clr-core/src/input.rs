@@ -0,0 +1,116 @@
+// 打开输入文件, "-" 表示标准输入
+// 错误信息都以文件名开头, 如 "foo.txt: No such file or directory (os error 2)"
+// 新的输入种类(如 mmap)也在这里添加, 工具中只调用 open
+use anyhow::{Result, anyhow};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
+
+const STDIN: &str = "-";
+
+// --------------------------------------------------
+pub fn is_stdin(filename: &str) -> bool {
+    filename == STDIN
+}
+
+// --------------------------------------------------
+pub fn open(filename: &str) -> Result<Box<dyn BufRead>> {
+    match filename {
+        STDIN => Ok(Box::new(BufReader::new(io::stdin()))),
+        _ => Ok(Box::new(BufReader::new(
+            File::open(filename).map_err(|e| anyhow!("{filename}: {e}"))?,
+        ))),
+    }
+}
+
+// --------------------------------------------------
+// 需要 seek 或 metadata 的工具(如 tailr)用这个打开;
+// "-" 时复制标准输入的文件描述符, 这样重定向的普通文件也能 seek
+pub fn open_file(filename: &str) -> Result<File> {
+    let file = match filename {
+        STDIN => stdin_file(),
+        _ => File::open(filename),
+    };
+    file.map_err(|e| anyhow!("{filename}: {e}"))
+}
+
+// --------------------------------------------------
+#[cfg(unix)]
+fn stdin_file() -> io::Result<File> {
+    use std::os::fd::AsFd;
+    io::stdin().as_fd().try_clone_to_owned().map(File::from)
+}
+
+#[cfg(windows)]
+fn stdin_file() -> io::Result<File> {
+    use std::os::windows::io::AsHandle;
+    io::stdin().as_handle().try_clone_to_owned().map(File::from)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn stdin_file() -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "standard input cannot be opened as a file",
+    ))
+}
+
+// --------------------------------------------------
+// 压缩的输入先解压, 其他输入与 open 相同
+#[cfg(feature = "decompress")]
+pub fn open_decompressed(filename: &str) -> Result<Box<dyn BufRead>> {
+    crate::decompress::decompress(open(filename)?, filename)
+        .map_err(|e| anyhow!("{filename}: {e}"))
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{is_stdin, open, open_file};
+    use std::io::{BufRead, Read, Write};
+
+    #[test]
+    fn test_open() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "foo\nbar\n").unwrap();
+        let filename = file.path().to_str().unwrap();
+        let lines: Vec<String> = open(filename)
+            .unwrap()
+            .lines()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(lines, ["foo", "bar"]);
+    }
+
+    #[test]
+    fn test_open_missing() {
+        let err = open("no-such-file").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "no-such-file: No such file or directory (os error 2)"
+        );
+    }
+
+    #[test]
+    fn test_open_file() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, "foo").unwrap();
+        let mut contents = String::new();
+        open_file(file.path().to_str().unwrap())
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        assert_eq!(contents, "foo");
+
+        let err = open_file("no-such-file").err().unwrap();
+        assert!(err.to_string().starts_with("no-such-file: "));
+    }
+
+    #[test]
+    fn test_is_stdin() {
+        assert!(is_stdin("-"));
+        assert!(!is_stdin("--"));
+        assert!(!is_stdin("./-"));
+    }
+}
@@ -0,0 +1,24 @@
+// clr-core: 各章工具共用的输入、输出和错误处理
+//   input:  打开输入, "-" 表示标准输入; decompress 特性提供压缩文件的读取
+//   output: 加锁、带缓冲的输出; 写入关闭的管道时安静地退出
+//   error:  统一的 "prog: file: error" 错误信息和退出码
+//
+// 每个工具的 main 都是这样:
+//   fn main() {
+//       clr_core::exit(run(Args::parse()));
+//   }
+// run 中跳过出错的文件时用 clr_core::warn 报告, 最后以 1 退出;
+// 不算错误的提示用 clr_core::notice
+pub mod error;
+pub mod input;
+pub mod output;
+
+#[cfg(feature = "decompress")]
+pub mod decompress;
+
+pub use error::{exit, notice, warn};
+pub use input::{is_stdin, open, open_file};
+pub use output::{create, stdout};
+
+#[cfg(feature = "decompress")]
+pub use input::open_decompressed;
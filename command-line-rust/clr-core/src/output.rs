@@ -0,0 +1,77 @@
+// 工具的输出: 标准输出加锁后只取一次, 不是终端时整块缓冲
+// 用 write!/writeln! 代替 print!/println!, 这样写入关闭的管道(如 | head)
+// 得到的是 BrokenPipe 错误而不是 panic, 由 error::exit 安静地处理
+// 返回的是 Box<dyn Write>, run 结束前要调用 flush() 才能发现写入错误
+use anyhow::{Result, anyhow};
+use std::{
+    fs::File,
+    io::{self, BufWriter, IsTerminal, Write},
+};
+
+use crate::input::is_stdin;
+
+// --------------------------------------------------
+pub fn stdout() -> Box<dyn Write> {
+    let stdout = io::stdout().lock();
+    // 终端上保持行缓冲, 交互时每行立即显示
+    if stdout.is_terminal() {
+        Box::new(stdout)
+    } else {
+        Box::new(BufWriter::new(stdout))
+    }
+}
+
+// --------------------------------------------------
+// 创建输出文件, "-" 表示标准输出
+pub fn create(filename: &str) -> Result<Box<dyn Write>> {
+    if is_stdin(filename) {
+        return Ok(stdout());
+    }
+    let file =
+        File::create(filename).map_err(|e| anyhow!("{filename}: {e}"))?;
+    Ok(Box::new(BufWriter::new(file)))
+}
+
+// --------------------------------------------------
+// 错误链中是否有写入关闭的管道的错误
+pub fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
+// --------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::{create, is_broken_pipe};
+    use anyhow::anyhow;
+    use std::{
+        fs,
+        io::{self, Write},
+    };
+
+    #[test]
+    fn test_create() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+        let mut out = create(path.to_str().unwrap()).unwrap();
+        write!(out, "foo").unwrap();
+        out.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo");
+
+        let missing = dir.path().join("no/such/dir");
+        let err = create(missing.to_str().unwrap()).err().unwrap();
+        assert!(err.to_string().starts_with(missing.to_str().unwrap()));
+    }
+
+    #[test]
+    fn test_is_broken_pipe() {
+        let pipe = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(is_broken_pipe(&anyhow::Error::from(pipe)));
+        let other = io::Error::from(io::ErrorKind::NotFound);
+        assert!(!is_broken_pipe(&anyhow::Error::from(other)));
+        assert!(!is_broken_pipe(&anyhow!("broken pipe")));
+    }
+}
@@ -0,0 +1,129 @@
+// clr_core::stdout、warn 和 exit 的退出码只能在单独的进程中检查:
+// 这个测试程序没有 libtest 外壳, 设置了 CLR_CORE_CHILD 时扮演一个工具,
+// 否则启动自己作为子进程并检查它的输出和退出码. 各工具不再重复这些测试
+use anyhow::{Result, bail};
+use std::{
+    env,
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+const CHILD: &str = "CLR_CORE_CHILD";
+
+fn main() {
+    match env::var(CHILD).as_deref() {
+        Ok("yes") => clr_core::exit(yes()),
+        Ok("cat") => clr_core::exit(cat()),
+        Ok("fail") => clr_core::exit(fail()),
+        Ok("notice") => {
+            clr_core::notice("just so you know");
+            clr_core::exit(Ok(()))
+        }
+        _ => {
+            closed_pipe();
+            skips_bad_file();
+            error_exit();
+            notice();
+        }
+    }
+}
+
+// --------------------------------------------------
+// 像 yes 一样一直输出, 直到写入出错
+fn yes() -> Result<()> {
+    let mut out = clr_core::stdout();
+    loop {
+        writeln!(out, "y")?;
+    }
+}
+
+// --------------------------------------------------
+// 像 catr 一样依次输出各个文件, 跳过打不开的
+fn cat() -> Result<()> {
+    let mut out = clr_core::stdout();
+    for filename in env::args().skip(1) {
+        match clr_core::open(&filename) {
+            Err(e) => clr_core::warn(e),
+            Ok(mut file) => {
+                io::copy(&mut file, &mut out)?;
+            }
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+// --------------------------------------------------
+fn fail() -> Result<()> {
+    bail!("something went wrong")
+}
+
+// --------------------------------------------------
+// 以子进程模式启动自己; 错误信息的程序名是本测试程序的文件名
+fn child(mode: &str) -> (Command, String) {
+    let exe = env::current_exe().unwrap();
+    let prog = Path::new(&exe).file_stem().unwrap();
+    let prog = prog.to_string_lossy().into_owned();
+    let mut cmd = Command::new(exe);
+    cmd.env(CHILD, mode)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    (cmd, prog)
+}
+
+// --------------------------------------------------
+// 像 | head 一样在读完之前关闭管道: 不报错, 以 0 退出
+fn closed_pipe() {
+    let (mut cmd, _) = child("yes");
+    let mut child = cmd.spawn().unwrap();
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+// --------------------------------------------------
+// 跳过出错的文件, 输出其他的, 最后以 1 退出
+fn skips_bad_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let good = dir.path().join("good.txt");
+    std::fs::write(&good, "a\nb\n").unwrap();
+    let bad = dir.path().join("missing.txt");
+
+    let (mut cmd, prog) = child("cat");
+    let output = cmd.arg(&bad).arg(&good).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "a\nb\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!(
+            "{prog}: {}: No such file or directory (os error 2)\n",
+            bad.display()
+        )
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
+
+// --------------------------------------------------
+// run 返回的错误以 "prog: " 开头, 以 1 退出
+fn error_exit() {
+    let (mut cmd, prog) = child("fail");
+    let output = cmd.output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("{prog}: something went wrong\n")
+    );
+    assert_eq!(output.status.code(), Some(1));
+}
+
+// --------------------------------------------------
+// 提示也以 "prog: " 开头, 但仍以 0 退出
+fn notice() {
+    let (mut cmd, prog) = child("notice");
+    let output = cmd.output().unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr),
+        format!("{prog}: just so you know\n")
+    );
+    assert_eq!(output.status.code(), Some(0));
+}